@@ -0,0 +1,104 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::status;
+
+/// Time `iterations` status computations phase by phase and print p50/p95
+/// latency for each.
+pub fn run(repo_root: &Path, git_dir: &Path, common_dir: &Path, iterations: usize) {
+    let iterations = iterations.max(1);
+    let mut porcelain = Vec::with_capacity(iterations);
+    let mut parse = Vec::with_capacity(iterations);
+    let mut hidden = Vec::with_capacity(iterations);
+    let mut lfs = Vec::with_capacity(iterations);
+    let mut stash = Vec::with_capacity(iterations);
+    let mut state = Vec::with_capacity(iterations);
+    let mut total = Vec::with_capacity(iterations);
+
+    for _ in 0..iterations {
+        let start = Instant::now();
+        let output = status::run_porcelain(repo_root);
+        porcelain.push(start.elapsed());
+
+        let t = Instant::now();
+        let parsed = status::parse_porcelain_v2(&output);
+        parse.push(t.elapsed());
+
+        let t = Instant::now();
+        let _ = status::count_hidden(repo_root);
+        hidden.push(t.elapsed());
+
+        let t = Instant::now();
+        if status::uses_lfs(common_dir) {
+            let _ = status::lfs_pending(repo_root, parsed.upstream.as_deref());
+        }
+        lfs.push(t.elapsed());
+
+        let t = Instant::now();
+        let _ = status::stash_count(common_dir);
+        stash.push(t.elapsed());
+
+        let t = Instant::now();
+        let _ = status::detect_operation_state(git_dir);
+        state.push(t.elapsed());
+
+        total.push(start.elapsed());
+    }
+
+    println!("{} iterations on {}", iterations, repo_root.display());
+    println!();
+    println!("{:<12}{:<12}{:>12}{:>12}", "backend", "phase", "p50", "p95");
+    for (phase, samples) in [
+        ("porcelain", &mut porcelain),
+        ("parse", &mut parse),
+        ("hidden", &mut hidden),
+        ("lfs", &mut lfs),
+        ("stash", &mut stash),
+        ("state", &mut state),
+        ("total", &mut total),
+    ] {
+        samples.sort_unstable();
+        println!(
+            "{:<12}{:<12}{:>12}{:>12}",
+            "subprocess",
+            phase,
+            format_duration(percentile(samples, 50)),
+            format_duration(percentile(samples, 95)),
+        );
+    }
+    println!();
+    println!("native backend: not available in this build");
+}
+
+/// Nearest-rank percentile of an ascending-sorted, non-empty sample set.
+fn percentile(sorted: &[Duration], pct: usize) -> Duration {
+    let rank = (pct * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+fn format_duration(d: Duration) -> String {
+    format!("{:.3}ms", d.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(values: &[u64]) -> Vec<Duration> {
+        values.iter().map(|&v| Duration::from_millis(v)).collect()
+    }
+
+    #[test]
+    fn percentile_nearest_rank() {
+        let samples = ms(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+        assert_eq!(percentile(&samples, 50), Duration::from_millis(5));
+        assert_eq!(percentile(&samples, 95), Duration::from_millis(10));
+    }
+
+    #[test]
+    fn percentile_single_sample() {
+        let samples = ms(&[7]);
+        assert_eq!(percentile(&samples, 50), Duration::from_millis(7));
+        assert_eq!(percentile(&samples, 95), Duration::from_millis(7));
+    }
+}
@@ -1,9 +1,17 @@
-use clap::Parser;
-use std::path::PathBuf;
+use clap::builder::PossibleValuesParser;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::format;
+use crate::status;
 
 #[derive(Parser, Debug)]
 #[command(name = "git-status-watch", about = "Reactive git status watcher")]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Path to the git repository (defaults to current directory)
     pub path: Option<PathBuf>,
 
@@ -11,6 +19,42 @@ pub struct Cli {
     #[arg(long)]
     pub format: Option<String>,
 
+    /// Built-in output style (e.g. 'compact' for `main ✗⇡`)
+    #[arg(long, conflicts_with = "format", value_parser = preset_names())]
+    pub preset: Option<String>,
+
+    /// Preset colors as 'clean=#rrggbb,dirty=#rrggbb,conflicted=#rrggbb'; omitted keys keep their default
+    #[arg(long, env = "GIT_STATUS_WATCH_THEME", value_name = "COLORS")]
+    pub theme: Option<format::Theme>,
+
+    /// Include detail fields (conflict breakdown) in JSON output
+    #[arg(long, conflicts_with_all = ["format", "preset"])]
+    pub extended: bool,
+
+    /// Emit one delimited row per status with a fixed column order
+    #[arg(long, value_enum, conflicts_with_all = ["format", "preset", "extended"])]
+    pub output: Option<Output>,
+
+    /// Print a row of column names before the first --output row
+    #[arg(long, requires = "output")]
+    pub header: bool,
+
+    /// Also publish status as terminal user variables (OSC 1337 SetUserVar) on the tty
+    #[arg(long)]
+    pub osc: bool,
+
+    /// Fill {commit_subject} and {commit_author} (one extra git call per HEAD change)
+    #[arg(long)]
+    pub commit_info: bool,
+
+    /// Fill {tag}, {commits_since_tag}, and {version} from git describe (rerun only when refs change)
+    #[arg(long)]
+    pub describe: bool,
+
+    /// Fill {hidden_changes} from git ls-files (rerun only when the index or config changes)
+    #[arg(long)]
+    pub hidden_changes: bool,
+
     /// Print status once and exit
     #[arg(long)]
     pub once: bool,
@@ -19,7 +63,176 @@ pub struct Cli {
     #[arg(long, default_value = "75")]
     pub debounce_ms: u64,
 
+    /// Debounce window for .git ref/index events (defaults to --debounce-ms)
+    #[arg(long)]
+    pub git_debounce_ms: Option<u64>,
+
+    /// File watching backend; `auto` uses the platform's native one, or polls on network filesystems (naming the native one forces it there too)
+    #[arg(long, value_enum, default_value = "auto", global = true)]
+    pub backend: Backend,
+
+    /// Rescan interval for the poll backend (e.g. 500ms, 2s, 1m)
+    #[arg(long, default_value = "2s", value_parser = parse_duration, global = true)]
+    pub poll_interval: Duration,
+
     /// Print on every event even if status unchanged
     #[arg(long)]
     pub always_print: bool,
+
+    /// Diagnostic log verbosity (default: off, or info with --log-file)
+    #[arg(long, value_enum, global = true)]
+    pub log_level: Option<LogLevel>,
+
+    /// Write diagnostic logs to this file instead of stderr (at info unless --log-level is given)
+    #[arg(long, global = true)]
+    pub log_file: Option<PathBuf>,
+}
+
+impl Cli {
+    pub fn status_options(&self) -> status::Options {
+        status::Options {
+            commit_info: self.commit_info,
+            describe: self.describe,
+            hidden_changes: self.hidden_changes,
+        }
+    }
+
+    /// Output style for `repo_root`, which `--output` rows lead with.
+    pub fn style<'a>(&'a self, repo_root: &'a Path) -> format::Style<'a> {
+        if let Some(t) = &self.format {
+            format::Style::Template(t)
+        } else if let Some(p) = self.preset.as_deref().and_then(format::preset) {
+            format::Style::Preset(p, self.theme.unwrap_or(format::THEME))
+        } else if let Some(output) = self.output {
+            format::Style::Row(output.delimiter(), repo_root)
+        } else if self.extended {
+            format::Style::ExtendedJson
+        } else {
+            format::Style::Json
+        }
+    }
+}
+
+fn preset_names() -> PossibleValuesParser {
+    PossibleValuesParser::new(format::PRESETS.iter().map(|p| p.name))
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Check the environment and report problems that affect watching
+    Doctor {
+        /// Path to the git repository (defaults to current directory)
+        path: Option<PathBuf>,
+    },
+
+    /// Measure status computation latency per phase
+    Bench {
+        /// Path to the git repository (defaults to current directory)
+        path: Option<PathBuf>,
+
+        /// Number of status computations to time
+        #[arg(short = 'n', long, default_value = "50")]
+        iterations: usize,
+    },
+
+    /// Install git hooks that poke the watcher after commits, checkouts, merges, and rewrites
+    InstallHooks {
+        /// Path to the git repository (defaults to current directory)
+        path: Option<PathBuf>,
+    },
+
+    /// List repos with a running watcher, their PIDs, and last status
+    List {
+        /// Custom format string for the status column
+        #[arg(long)]
+        format: Option<String>,
+
+        /// Print one delimited row per repo instead of the table
+        #[arg(long, value_enum, conflicts_with = "format")]
+        output: Option<Output>,
+
+        /// Print a row of column names before the --output rows
+        #[arg(long, requires = "output")]
+        header: bool,
+    },
+
+    /// Print the last persisted status without running git
+    Read {
+        /// Path inside the git repository (defaults to current directory)
+        path: Option<PathBuf>,
+
+        /// Custom format string; `{stale}` renders when no watcher is running
+        #[arg(long)]
+        format: Option<String>,
+    },
+
+    /// Ask the running watcher for a repo to recompute immediately
+    Notify {
+        /// Path to the git repository (defaults to current directory)
+        path: Option<PathBuf>,
+    },
+}
+
+/// Only this platform's native backend is offered, since notify builds
+/// just that one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Backend {
+    Auto,
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    Inotify,
+    #[cfg(target_os = "macos")]
+    Fsevents,
+    #[cfg(any(target_os = "freebsd", target_os = "openbsd", target_os = "netbsd"))]
+    Kqueue,
+    Poll,
+    Watchman,
+}
+
+/// `500ms`, `2s`, `1m`, or a bare number of seconds.
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let (number, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => s.split_at(i),
+        None => (s, "s"),
+    };
+    let n: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration: {}", s))?;
+    let duration = match unit {
+        "ms" => Duration::from_millis(n),
+        "s" => Duration::from_secs(n),
+        "m" => Duration::from_secs(
+            n.checked_mul(60)
+                .ok_or_else(|| format!("invalid duration: {}", s))?,
+        ),
+        _ => return Err(format!("invalid duration unit in {} (use ms, s, or m)", s)),
+    };
+    if duration.is_zero() {
+        return Err("duration must be greater than zero".into());
+    }
+    Ok(duration)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Output {
+    Tsv,
+    Csv,
+}
+
+impl Output {
+    pub fn delimiter(self) -> format::Delimiter {
+        match self {
+            Output::Tsv => format::Delimiter::Tab,
+            Output::Csv => format::Delimiter::Comma,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
 }
@@ -0,0 +1,283 @@
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use crate::state;
+use crate::status;
+use crate::watcher::{self, Backend};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Severity {
+    Ok,
+    Info,
+    Warn,
+    Error,
+}
+
+struct Finding {
+    severity: Severity,
+    message: String,
+    hint: Option<String>,
+}
+
+impl Finding {
+    fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Finding {
+            severity,
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    fn hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+}
+
+/// Minimum git version with `status --porcelain=v2`.
+const MIN_GIT: (u32, u32) = (2, 11);
+
+/// Run every check, print the findings, and return whether nothing needs
+/// attention (no warnings or errors).
+pub fn run(repo_root: &Path, state_dir: &Path, state_path: &Path, backend: Backend) -> bool {
+    let mut findings = vec![check_git_version(), check_backend(repo_root, backend)];
+    if backend == Backend::Inotify {
+        findings.extend(check_inotify(repo_root, state_path));
+    }
+    findings.push(check_leader(state_path));
+    findings.extend(check_state_dir(state_dir));
+    findings.extend(check_git_config(repo_root));
+
+    for f in &findings {
+        let label = match f.severity {
+            Severity::Ok => "ok",
+            Severity::Info => "info",
+            Severity::Warn => "warn",
+            Severity::Error => "error",
+        };
+        println!("{:<6}{}", label, f.message);
+        if let Some(hint) = &f.hint {
+            println!("{:<6}{}", "", hint);
+        }
+    }
+
+    findings
+        .iter()
+        .all(|f| matches!(f.severity, Severity::Ok | Severity::Info))
+}
+
+fn check_git_version() -> Finding {
+    let output = status::run_git(Path::new("."), &["--version"]);
+    match parse_git_version(&output) {
+        Some((major, minor)) if (major, minor) >= MIN_GIT => {
+            Finding::new(Severity::Ok, output.trim())
+        }
+        Some(_) => {
+            Finding::new(Severity::Error, format!("{} is too old", output.trim())).hint(format!(
+                "git {}.{}+ is required for `status --porcelain=v2`",
+                MIN_GIT.0, MIN_GIT.1
+            ))
+        }
+        None => Finding::new(Severity::Error, "git not found on PATH"),
+    }
+}
+
+fn parse_git_version(output: &str) -> Option<(u32, u32)> {
+    let version = output
+        .strip_prefix("git version ")?
+        .split_whitespace()
+        .next()?;
+    let mut parts = version.split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+fn check_backend(repo_root: &Path, backend: Backend) -> Finding {
+    match backend {
+        Backend::Poll(_) => match watcher::network_fs(repo_root) {
+            Some(fs) => Finding::new(
+                Severity::Ok,
+                format!("watch backend: {} (repo is on {})", backend, fs),
+            ),
+            None => Finding::new(Severity::Ok, format!("watch backend: {}", backend))
+                .hint("polling rescans the whole tree; --backend auto uses native events here"),
+        },
+        Backend::Watchman => {
+            let found = process::Command::new("watchman-wait")
+                .arg("--help")
+                .stdout(process::Stdio::null())
+                .stderr(process::Stdio::null())
+                .status()
+                .is_ok();
+            if found {
+                Finding::new(Severity::Ok, "watch backend: watchman")
+            } else {
+                Finding::new(
+                    Severity::Error,
+                    "watch backend: watchman, but watchman-wait is not on PATH",
+                )
+                .hint("install watchman or pick another --backend")
+            }
+        }
+        backend => Finding::new(Severity::Ok, format!("watch backend: {}", backend)),
+    }
+}
+
+/// The user's inotify watches against their limit, plus what the running
+/// leader holds, or the directory count a new one would add.
+#[cfg(target_os = "linux")]
+fn check_inotify(repo_root: &Path, state_path: &Path) -> Option<Finding> {
+    let in_use = watcher::watch_budget()?;
+    let held = state::leader_pid(state_path).and_then(watcher::process_watches);
+    let (message, budget) = match held {
+        Some(held) if held > 0 => (
+            format!(
+                "watcher holds {} inotify watches; {} of {} in use by this user",
+                held, in_use.used, in_use.limit
+            ),
+            in_use,
+        ),
+        _ => {
+            let dirs = count_dirs(repo_root);
+            (
+                format!(
+                    "{} directories to watch; {} of {} inotify watches in use by this user",
+                    dirs, in_use.used, in_use.limit
+                ),
+                watcher::WatchBudget {
+                    used: in_use.used + dirs,
+                    limit: in_use.limit,
+                },
+            )
+        }
+    };
+    let hint = "raise it: sudo sysctl fs.inotify.max_user_watches=524288";
+    Some(if budget.is_over() {
+        Finding::new(Severity::Warn, format!("{}: over the limit", message)).hint(hint)
+    } else if budget.is_near() {
+        Finding::new(Severity::Warn, format!("{}: close to the limit", message)).hint(hint)
+    } else {
+        Finding::new(Severity::Ok, message)
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn check_inotify(_repo_root: &Path, _state_path: &Path) -> Option<Finding> {
+    None
+}
+
+/// Count directories a recursive watch would register, without following
+/// symlinks.
+#[cfg(target_os = "linux")]
+fn count_dirs(root: &Path) -> u64 {
+    let mut count = 0;
+    let mut stack = vec![root.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        count += 1;
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                stack.push(entry.path());
+            }
+        }
+    }
+    count
+}
+
+fn check_leader(state_path: &Path) -> Finding {
+    if state::is_watched(state_path) {
+        Finding::new(Severity::Ok, "a watcher is running for this repo")
+    } else {
+        Finding::new(Severity::Info, "no watcher is running for this repo")
+            .hint("--once will run git on every call until one is started")
+    }
+}
+
+/// Stale paths shown before the list is truncated.
+const MAX_LISTED: usize = 5;
+
+/// Leftover temp files and state for repos that no longer exist.
+fn check_state_dir(state_dir: &Path) -> Option<Finding> {
+    let entries = fs::read_dir(state_dir).ok()?;
+    let mut stale = Vec::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.ends_with(".tmp") {
+            stale.push(entry.path());
+            continue;
+        }
+        let base = name.strip_suffix(".lock").unwrap_or(&name);
+        if !state::repo_from_file_name(base).exists() {
+            stale.push(entry.path());
+        }
+    }
+
+    if stale.is_empty() {
+        return Some(Finding::new(
+            Severity::Ok,
+            format!("no stale files in {}", state_dir.display()),
+        ));
+    }
+    let mut list = stale
+        .iter()
+        .take(MAX_LISTED)
+        .map(|p| p.display().to_string())
+        .collect::<Vec<_>>()
+        .join(" ");
+    if stale.len() > MAX_LISTED {
+        list.push_str(&format!(" (and {} more)", stale.len() - MAX_LISTED));
+    }
+    Some(
+        Finding::new(
+            Severity::Warn,
+            format!("{} stale files in {}", stale.len(), state_dir.display()),
+        )
+        .hint(format!("safe to delete: {}", list)),
+    )
+}
+
+fn check_git_config(repo_root: &Path) -> Vec<Finding> {
+    let fsmonitor = status::run_git(repo_root, &["config", "--get", "core.fsmonitor"]);
+    let untracked_cache = status::run_git(repo_root, &["config", "--get", "core.untrackedCache"]);
+
+    let mut findings = Vec::new();
+    match fsmonitor.trim() {
+        "" | "false" => findings.push(
+            Finding::new(Severity::Info, "core.fsmonitor is disabled")
+                .hint("large repos: git config core.fsmonitor true"),
+        ),
+        _ => findings.push(Finding::new(Severity::Ok, "core.fsmonitor is enabled")),
+    }
+    match untracked_cache.trim() {
+        "true" => findings.push(Finding::new(Severity::Ok, "core.untrackedCache is enabled")),
+        _ => findings.push(
+            Finding::new(Severity::Info, "core.untrackedCache is disabled")
+                .hint("large repos: git config core.untrackedCache true"),
+        ),
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn git_version_parsing() {
+        assert_eq!(parse_git_version("git version 2.43.0\n"), Some((2, 43)));
+        assert_eq!(
+            parse_git_version("git version 2.39.3 (Apple Git-145)"),
+            Some((2, 39))
+        );
+        assert_eq!(
+            parse_git_version("git version 2.45.1.windows.1"),
+            Some((2, 45))
+        );
+        assert_eq!(parse_git_version(""), None);
+    }
+}
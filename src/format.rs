@@ -1,11 +1,216 @@
-use std::fmt::Write;
+use std::fmt::{self, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
 
-use crate::types::GitStatus;
+use serde::Serialize;
+
+use crate::types::{ConflictCounts, GitStatus};
+
+/// How a status is rendered to a line of output.
+#[derive(Clone, Copy)]
+pub enum Style<'a> {
+    Json,
+    /// JSON including detail fields such as the conflict breakdown.
+    ExtendedJson,
+    Template(&'a str),
+    Preset(&'static Preset, Theme),
+    /// One `COLUMNS` row per status for the given repo, for
+    /// `--output tsv|csv`.
+    Row(Delimiter, &'a Path),
+}
+
+pub fn render(status: &GitStatus, style: Style) -> String {
+    match style {
+        Style::Json => format_json(status),
+        Style::ExtendedJson => format_extended_json(status),
+        Style::Template(t) => format_custom(status, t),
+        Style::Preset(p, theme) => (p.render)(status, &theme),
+        Style::Row(d, repo) => format_row(status, repo, d),
+    }
+}
+
+/// A named built-in output style for `--preset`.
+pub struct Preset {
+    pub name: &'static str,
+    render: fn(&GitStatus, &Theme) -> String,
+}
+
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "compact",
+        render: render_compact,
+    },
+    Preset {
+        name: "polybar",
+        render: render_polybar,
+    },
+    Preset {
+        name: "i3blocks",
+        render: render_i3blocks,
+    },
+];
+
+/// An RGB color, written and parsed as `#rrggbb`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Color(pub [u8; 3]);
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let [r, g, b] = self.0;
+        write!(f, "#{:02x}{:02x}{:02x}", r, g, b)
+    }
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("`{}` is not a #rrggbb color", s);
+        let hex = s.strip_prefix('#').ok_or_else(invalid)?;
+        if hex.len() != 6 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+        Ok(Color([channel(0)?, channel(2)?, channel(4)?]))
+    }
+}
+
+/// Colors for presets that color by repo state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    pub clean: Color,
+    pub dirty: Color,
+    pub conflicted: Color,
+}
+
+/// The default palette (One Dark's green, yellow, and red).
+pub const THEME: Theme = Theme {
+    clean: Color([0x98, 0xc3, 0x79]),
+    dirty: Color([0xe5, 0xc0, 0x7b]),
+    conflicted: Color([0xe0, 0x6c, 0x75]),
+};
+
+/// `clean=#rrggbb,dirty=#rrggbb,conflicted=#rrggbb`, as given to `--theme`.
+/// Keys left out keep their default color.
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut theme = THEME;
+        for entry in s.split(',').filter(|e| !e.trim().is_empty()) {
+            let (key, value) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("expected key=#rrggbb, got `{}`", entry))?;
+            let slot = match key.trim() {
+                "clean" => &mut theme.clean,
+                "dirty" => &mut theme.dirty,
+                "conflicted" => &mut theme.conflicted,
+                other => {
+                    return Err(format!(
+                        "unknown theme key `{}` (expected clean, dirty, or conflicted)",
+                        other
+                    ))
+                }
+            };
+            *slot = value.trim().parse()?;
+        }
+        Ok(theme)
+    }
+}
+
+impl Theme {
+    /// Conflicts (or a stopped operation) win over dirtiness.
+    fn color(&self, status: &GitStatus) -> Color {
+        if status.conflicted > 0 || status.state_flags.conflicted {
+            self.conflicted
+        } else if status.dirty {
+            self.dirty
+        } else {
+            self.clean
+        }
+    }
+}
+
+pub fn preset(name: &str) -> Option<&'static Preset> {
+    PRESETS.iter().find(|p| p.name == name)
+}
+
+/// `main ✓`, `main ✗⇡`, `main ✓⇕`: branch, dirtiness glyph, sync arrow.
+fn render_compact(status: &GitStatus, _theme: &Theme) -> String {
+    let glyph = if status.dirty { '✗' } else { '✓' };
+    let sync = match (status.ahead > 0, status.behind > 0) {
+        (true, true) => "⇕",
+        (true, false) => "⇡",
+        (false, true) => "⇣",
+        (false, false) => "",
+    };
+    format!("{} {}{}", status.branch, glyph, sync)
+}
+
+/// The compact text wrapped in a polybar foreground tag:
+/// `%{F#e5c07b}main ✗⇡%{F-}`. Ref names may contain `%{`, so `%` is
+/// doubled to keep a branch from injecting polybar tags.
+fn render_polybar(status: &GitStatus, theme: &Theme) -> String {
+    format!(
+        "%{{F{}}}{}%{{F-}}",
+        theme.color(status),
+        render_compact(status, theme).replace('%', "%%")
+    )
+}
+
+/// i3blocks' three-line block protocol: full text, short text (the
+/// branch), and color.
+fn render_i3blocks(status: &GitStatus, theme: &Theme) -> String {
+    format!(
+        "{}\n{}\n{}",
+        render_compact(status, theme),
+        status.branch,
+        theme.color(status)
+    )
+}
+
+/// A status serialized with its detail fields, borrowing rather than
+/// cloning it.
+#[derive(Serialize)]
+pub struct Extended<'a> {
+    #[serde(flatten)]
+    pub status: &'a GitStatus,
+    pub conflicts: &'a ConflictCounts,
+}
+
+impl<'a> Extended<'a> {
+    pub fn new(status: &'a GitStatus) -> Self {
+        Extended {
+            status,
+            conflicts: &status.conflicts,
+        }
+    }
+}
 
 pub fn format_json(status: &GitStatus) -> String {
     serde_json::to_string(status).unwrap()
 }
 
+pub fn format_extended_json(status: &GitStatus) -> String {
+    serde_json::to_string(&Extended::new(status)).unwrap()
+}
+
+/// JSON for a status read from the state file, with a `stale` flag set when
+/// no watcher is keeping it current.
+pub fn format_cached_json(status: &GitStatus, stale: bool) -> String {
+    let mut json = format_json(status);
+    json.pop();
+    let _ = write!(json, ",\"stale\":{}}}", stale);
+    json
+}
+
+/// Like `format_custom`, with `{stale}` rendering as `stale` or empty.
+pub fn format_cached_custom(status: &GitStatus, template: &str, stale: bool) -> String {
+    let template = template.replace("{stale}", if stale { "stale" } else { "" });
+    format_custom(status, &template)
+}
+
 pub fn format_custom(status: &GitStatus, template: &str) -> String {
     let bytes = template.as_bytes();
     let len = bytes.len();
@@ -18,19 +223,8 @@ pub fn format_custom(status: &GitStatus, template: &str) -> String {
                 if let Some(end) = template[i + 1..].find('}') {
                     let close = i + 1 + end;
                     let key = &template[i + 1..close];
-                    match key {
-                        "branch" => result.push_str(&status.branch),
-                        "staged" => result.push_str(ibuf.format(status.staged)),
-                        "modified" => result.push_str(ibuf.format(status.modified)),
-                        "untracked" => result.push_str(ibuf.format(status.untracked)),
-                        "conflicted" => result.push_str(ibuf.format(status.conflicted)),
-                        "ahead" => result.push_str(ibuf.format(status.ahead)),
-                        "behind" => result.push_str(ibuf.format(status.behind)),
-                        "stash" => result.push_str(ibuf.format(status.stash)),
-                        "state" => {
-                            let _ = write!(result, "{}", status.state);
-                        }
-                        _ => result.push_str(&template[i..close + 1]),
+                    if !push_field(&mut result, status, key, &mut ibuf) {
+                        result.push_str(&template[i..close + 1]);
                     }
                     i = close + 1;
                 } else {
@@ -58,10 +252,133 @@ pub fn format_custom(status: &GitStatus, template: &str) -> String {
     result
 }
 
+/// Append the value of placeholder `key`; false if `key` is not a field.
+fn push_field(out: &mut String, status: &GitStatus, key: &str, ibuf: &mut itoa::Buffer) -> bool {
+    match key {
+        "branch" => out.push_str(&status.branch),
+        "staged" => out.push_str(ibuf.format(status.staged)),
+        "modified" => out.push_str(ibuf.format(status.modified)),
+        "untracked" => out.push_str(ibuf.format(status.untracked)),
+        "conflicted" => out.push_str(ibuf.format(status.conflicted)),
+        "typechange" => out.push_str(ibuf.format(status.typechange)),
+        "hidden_changes" => out.push_str(ibuf.format(status.hidden_changes)),
+        "lfs_pending" => out.push_str(ibuf.format(status.lfs_pending)),
+        "ahead" => out.push_str(ibuf.format(status.ahead)),
+        "behind" => out.push_str(ibuf.format(status.behind)),
+        "remote" => out.push_str(&status.remote),
+        "remote_host" => out.push_str(&status.remote_host),
+        "stash" => out.push_str(ibuf.format(status.stash)),
+        "state" => out.push_str(&status.state_label()),
+        "merging" => out.push_str(&status.merging),
+        "op_remaining" => out.push_str(ibuf.format(status.op_remaining)),
+        "commit_subject" => out.push_str(&status.commit_subject),
+        "commit_author" => out.push_str(&status.commit_author),
+        "tag" => out.push_str(&status.tag),
+        "commits_since_tag" => out.push_str(ibuf.format(status.commits_since_tag)),
+        "version" => out.push_str(&status.version),
+        "dirty" => out.push_str(if status.dirty { "true" } else { "false" }),
+        "changes" => out.push_str(ibuf.format(status.changes)),
+        _ => return false,
+    }
+    true
+}
+
+/// Column order for `--output tsv|csv`. New fields are only ever appended
+/// so scripts indexing columns by position keep working.
+pub const COLUMNS: &[&str] = &[
+    "repo",
+    "branch",
+    "staged",
+    "modified",
+    "untracked",
+    "conflicted",
+    "typechange",
+    "hidden_changes",
+    "lfs_pending",
+    "ahead",
+    "behind",
+    "remote",
+    "remote_host",
+    "stash",
+    "state",
+    "merging",
+    "op_remaining",
+    "commit_subject",
+    "commit_author",
+    "tag",
+    "commits_since_tag",
+    "version",
+    "dirty",
+    "changes",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delimiter {
+    Tab,
+    Comma,
+}
+
+/// The `COLUMNS` names joined by `delimiter`.
+pub fn format_header(delimiter: Delimiter) -> String {
+    COLUMNS.join(match delimiter {
+        Delimiter::Tab => "\t",
+        Delimiter::Comma => ",",
+    })
+}
+
+/// One row of `COLUMNS` values, led by the repo path so rows from many
+/// repos can be told apart. TSV fields have tabs and newlines replaced by
+/// spaces; CSV fields are quoted per RFC 4180 when needed.
+pub fn format_row(status: &GitStatus, repo: &Path, delimiter: Delimiter) -> String {
+    let mut result = String::with_capacity(128);
+    let mut ibuf = itoa::Buffer::new();
+    let mut value = String::new();
+    for (n, key) in COLUMNS.iter().enumerate() {
+        if n > 0 {
+            result.push(match delimiter {
+                Delimiter::Tab => '\t',
+                Delimiter::Comma => ',',
+            });
+        }
+        value.clear();
+        if *key == "repo" {
+            value.push_str(&repo.to_string_lossy());
+        } else {
+            push_field(&mut value, status, key, &mut ibuf);
+        }
+        match delimiter {
+            Delimiter::Tab => {
+                result.extend(value.chars().map(|c| match c {
+                    '\t' | '\n' | '\r' => ' ',
+                    c => c,
+                }));
+            }
+            Delimiter::Comma if value.contains([',', '"', '\n', '\r']) => {
+                result.push('"');
+                result.push_str(&value.replace('"', "\"\""));
+                result.push('"');
+            }
+            Delimiter::Comma => result.push_str(&value),
+        }
+    }
+    result
+}
+
+/// Coarse human-readable age: `42s`, `5m`, `3h`, `2d`.
+pub fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m", secs / 60),
+        3600..=86399 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::OperationState;
+    use crate::types::{OperationState, StateFlags};
 
     fn sample_status() -> GitStatus {
         GitStatus {
@@ -70,10 +387,26 @@ mod tests {
             modified: 3,
             untracked: 1,
             conflicted: 0,
+            typechange: 1,
+            hidden_changes: 0,
+            lfs_pending: 0,
             ahead: 1,
             behind: 0,
+            remote: String::new(),
+            remote_host: String::new(),
             stash: 2,
             state: OperationState::Clean,
+            state_flags: StateFlags::default(),
+            merging: String::new(),
+            op_remaining: 0,
+            commit_subject: String::new(),
+            commit_author: String::new(),
+            tag: String::new(),
+            commits_since_tag: 0,
+            version: String::new(),
+            dirty: true,
+            changes: 6,
+            conflicts: ConflictCounts::default(),
         }
     }
 
@@ -87,10 +420,34 @@ mod tests {
         assert_eq!(parsed["modified"], 3);
         assert_eq!(parsed["untracked"], 1);
         assert_eq!(parsed["conflicted"], 0);
+        assert_eq!(parsed["typechange"], 1);
         assert_eq!(parsed["ahead"], 1);
         assert_eq!(parsed["behind"], 0);
         assert_eq!(parsed["stash"], 2);
         assert_eq!(parsed["state"], "clean");
+        assert_eq!(parsed["dirty"], true);
+        assert_eq!(parsed["changes"], 6);
+        assert!(parsed.get("conflicts").is_none());
+    }
+
+    #[test]
+    fn extended_json_includes_conflicts() {
+        let mut s = sample_status();
+        s.conflicts = ConflictCounts {
+            both_modified: 2,
+            deleted_by_them: 1,
+            ..Default::default()
+        };
+        let parsed: serde_json::Value =
+            serde_json::from_str(&render(&s, Style::ExtendedJson)).unwrap();
+        assert_eq!(parsed["conflicts"]["both_modified"], 2);
+        assert_eq!(parsed["conflicts"]["deleted_by_us"], 0);
+        assert_eq!(parsed["conflicts"]["deleted_by_them"], 1);
+        assert_eq!(parsed["conflicts"]["both_added"], 0);
+
+        // The state file is written in this form and must read back whole
+        let back: GitStatus = serde_json::from_str(&format_extended_json(&s)).unwrap();
+        assert_eq!(back, s);
     }
 
     #[test]
@@ -108,6 +465,21 @@ mod tests {
         assert_eq!(result, "main|rebase");
     }
 
+    #[test]
+    fn custom_format_state_flags() {
+        let mut s = sample_status();
+        s.state = OperationState::Rebase;
+        s.state_flags.paused = true;
+        assert_eq!(format_custom(&s, "{state}"), "rebase:paused");
+        s.state_flags = StateFlags {
+            paused: false,
+            conflicted: true,
+        };
+        assert_eq!(format_custom(&s, "{state}"), "rebase:conflicted");
+        s.state = OperationState::Clean;
+        assert_eq!(format_custom(&s, "{state}"), "");
+    }
+
     #[test]
     fn custom_format_clean_state_empty() {
         let s = sample_status();
@@ -115,6 +487,15 @@ mod tests {
         assert_eq!(result, "main");
     }
 
+    #[test]
+    fn custom_format_dirty_and_changes() {
+        let mut s = sample_status();
+        assert_eq!(format_custom(&s, "{dirty} {changes}"), "true 6");
+        s.dirty = false;
+        s.changes = 0;
+        assert_eq!(format_custom(&s, "{dirty} {changes}"), "false 0");
+    }
+
     #[test]
     fn custom_format_tab_separated() {
         let s = sample_status();
@@ -124,4 +505,116 @@ mod tests {
         );
         assert_eq!(result, "main\t2\t3\t1\t0\t1\t0\t2\t");
     }
+
+    #[test]
+    fn compact_preset() {
+        let compact = preset("compact").unwrap();
+        let mut s = sample_status();
+        assert_eq!(render(&s, Style::Preset(compact, THEME)), "main ✗⇡");
+        s.dirty = false;
+        s.ahead = 0;
+        assert_eq!(render(&s, Style::Preset(compact, THEME)), "main ✓");
+        s.ahead = 2;
+        s.behind = 1;
+        assert_eq!(render(&s, Style::Preset(compact, THEME)), "main ✓⇕");
+        assert!(preset("nope").is_none());
+    }
+
+    #[test]
+    fn bar_presets() {
+        let polybar = preset("polybar").unwrap();
+        let i3blocks = preset("i3blocks").unwrap();
+        let mut s = sample_status();
+        assert_eq!(
+            render(&s, Style::Preset(polybar, THEME)),
+            format!("%{{F{}}}main ✗⇡%{{F-}}", THEME.dirty)
+        );
+        assert_eq!(
+            render(&s, Style::Preset(i3blocks, THEME)),
+            format!("main ✗⇡\nmain\n{}", THEME.dirty)
+        );
+        s.branch = "x%{A:reboot:}".into();
+        assert_eq!(
+            render(&s, Style::Preset(polybar, THEME)),
+            format!("%{{F{}}}x%%{{A:reboot:}} ✗⇡%{{F-}}", THEME.dirty)
+        );
+        s.branch = "main".into();
+        s.conflicted = 1;
+        assert!(render(&s, Style::Preset(polybar, THEME)).contains(&THEME.conflicted.to_string()));
+        s.conflicted = 0;
+        s.dirty = false;
+        assert!(render(&s, Style::Preset(i3blocks, THEME)).ends_with(&THEME.clean.to_string()));
+    }
+
+    #[test]
+    fn theme_overrides() {
+        assert_eq!(THEME.dirty.to_string(), "#e5c07b");
+        assert_eq!("".parse::<Theme>().unwrap(), THEME);
+
+        let theme: Theme = "dirty=#FF8800, conflicted=#ff0000".parse().unwrap();
+        assert_eq!(theme.clean, THEME.clean);
+        assert_eq!(theme.dirty, Color([0xff, 0x88, 0x00]));
+        assert_eq!(theme.conflicted.to_string(), "#ff0000");
+
+        let polybar = preset("polybar").unwrap();
+        let s = sample_status();
+        assert_eq!(
+            render(&s, Style::Preset(polybar, theme)),
+            "%{F#ff8800}main ✗⇡%{F-}"
+        );
+
+        assert!("dirty=ff8800".parse::<Theme>().is_err());
+        assert!("dirty=#ff88".parse::<Theme>().is_err());
+        assert!("dirty=#gg8800".parse::<Theme>().is_err());
+        assert!("staged=#ff8800".parse::<Theme>().is_err());
+        assert!("dirty".parse::<Theme>().is_err());
+    }
+
+    #[test]
+    fn delimited_rows() {
+        let mut s = sample_status();
+        let repo = Path::new("/src/app");
+        let header = format_header(Delimiter::Comma);
+        assert!(header.starts_with("repo,branch,staged,modified,"));
+        assert_eq!(header.split(',').count(), COLUMNS.len());
+
+        let row = format_row(&s, repo, Delimiter::Tab);
+        assert!(
+            row.starts_with("/src/app\tmain\t2\t3\t1\t0\t1\t"),
+            "row: {}",
+            row
+        );
+        assert_eq!(row.split('\t').count(), COLUMNS.len());
+
+        s.commit_subject = "fix: a, \"b\"\tc".to_string();
+        let tsv = format_row(&s, repo, Delimiter::Tab);
+        assert_eq!(tsv.split('\t').count(), COLUMNS.len());
+        assert!(tsv.contains("\tfix: a, \"b\" c\t"), "tsv: {}", tsv);
+        let csv = format_row(&s, Path::new("/src/a,b"), Delimiter::Comma);
+        assert!(csv.starts_with("\"/src/a,b\",main,"), "csv: {}", csv);
+        assert!(csv.contains(",\"fix: a, \"\"b\"\"\tc\","), "csv: {}", csv);
+    }
+
+    #[test]
+    fn cached_output_marks_stale() {
+        let s = sample_status();
+        let parsed: serde_json::Value =
+            serde_json::from_str(&format_cached_json(&s, true)).unwrap();
+        assert_eq!(parsed["stale"], true);
+        assert_eq!(parsed["branch"], "main");
+        assert_eq!(
+            format_cached_custom(&s, "{branch} {stale}", true),
+            "main stale"
+        );
+        assert_eq!(format_cached_custom(&s, "{branch}{stale}", false), "main");
+    }
+
+    #[test]
+    fn age_units() {
+        assert_eq!(format_age(Duration::from_secs(0)), "0s");
+        assert_eq!(format_age(Duration::from_secs(59)), "59s");
+        assert_eq!(format_age(Duration::from_secs(90)), "1m");
+        assert_eq!(format_age(Duration::from_secs(7200)), "2h");
+        assert_eq!(format_age(Duration::from_secs(3 * 86400)), "3d");
+    }
 }
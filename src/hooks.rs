@@ -0,0 +1,181 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::status;
+
+/// Hooks that fire after git rewrites refs or the worktree.
+pub const HOOKS: &[&str] = &["post-commit", "post-checkout", "post-merge", "post-rewrite"];
+
+/// File inside the git dir that hooks touch to poke a running watcher.
+pub const REFRESH_FILE: &str = "git-status-watch.refresh";
+
+const BEGIN: &str = "# >>> git-status-watch >>>";
+const END: &str = "# <<< git-status-watch <<<";
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Created,
+    Augmented,
+    Updated,
+    Skipped(&'static str),
+}
+
+/// Install or refresh our snippet in each hook under the repo's hooks dir
+/// (honours `core.hooksPath`).
+pub fn install(repo_root: &Path) -> io::Result<Vec<(&'static str, Outcome)>> {
+    let dir = hooks_dir(repo_root);
+    fs::create_dir_all(&dir)?;
+
+    let mut results = Vec::with_capacity(HOOKS.len());
+    for &name in HOOKS {
+        let path = dir.join(name);
+        let existing = match fs::read_to_string(&path) {
+            Ok(s) => Some(s),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        let outcome = match merge_hook(existing.as_deref()) {
+            Ok((content, outcome)) => {
+                fs::write(&path, content)?;
+                make_executable(&path)?;
+                outcome
+            }
+            Err(reason) => Outcome::Skipped(reason),
+        };
+        results.push((name, outcome));
+    }
+    Ok(results)
+}
+
+fn hooks_dir(repo_root: &Path) -> PathBuf {
+    let out = status::run_git(repo_root, &["rev-parse", "--git-path", "hooks"]);
+    let dir = PathBuf::from(out.trim());
+    if dir.is_absolute() {
+        dir
+    } else {
+        repo_root.join(dir)
+    }
+}
+
+/// Signal the watcher directly; fall back to touching the refresh file when
+/// the binary is not on the hook's PATH.
+fn snippet() -> String {
+    format!(
+        "{}\ngit-status-watch notify >/dev/null 2>&1 ||\n  touch \"$(git rev-parse --git-dir)/{}\" 2>/dev/null || true\n{}\n",
+        BEGIN, REFRESH_FILE, END
+    )
+}
+
+/// Compute the new hook content. Existing hooks keep their body; a previous
+/// snippet is replaced in place so reinstalling is idempotent.
+fn merge_hook(existing: Option<&str>) -> Result<(String, Outcome), &'static str> {
+    let Some(existing) = existing else {
+        return Ok((format!("#!/bin/sh\n{}", snippet()), Outcome::Created));
+    };
+
+    if let (Some(start), Some(end)) = (existing.find(BEGIN), existing.find(END)) {
+        if start < end {
+            let mut tail = &existing[end + END.len()..];
+            tail = tail.strip_prefix('\n').unwrap_or(tail);
+            let content = format!("{}{}{}", &existing[..start], snippet(), tail);
+            return Ok((content, Outcome::Updated));
+        }
+    }
+
+    let first = existing.lines().next().unwrap_or("");
+    if !is_shell_shebang(first) {
+        return Err("not a shell script");
+    }
+    // Insert right after the shebang: appending would never run if the
+    // existing body ends in `exit` or `exec`.
+    let body = existing.get(first.len() + 1..).unwrap_or("");
+    Ok((
+        format!("{}\n{}{}", first, snippet(), body),
+        Outcome::Augmented,
+    ))
+}
+
+/// Whether a `#!` line runs a POSIX-ish shell, either directly
+/// (`#!/bin/bash`) or through `env` (`#!/usr/bin/env zsh`).
+fn is_shell_shebang(line: &str) -> bool {
+    let Some(cmd) = line.strip_prefix("#!") else {
+        return false;
+    };
+    let mut words = cmd.split_whitespace();
+    let mut interp = words.next().unwrap_or("");
+    if basename(interp) == "env" {
+        interp = words.find(|w| !w.starts_with('-')).unwrap_or("");
+    }
+    matches!(basename(interp), "sh" | "bash" | "zsh" | "dash" | "ksh")
+}
+
+fn basename(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mut perms = fs::metadata(path)?.permissions();
+    perms.set_mode(perms.mode() | 0o755);
+    fs::set_permissions(path, perms)
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn creates_new_hook() {
+        let (content, outcome) = merge_hook(None).unwrap();
+        assert_eq!(outcome, Outcome::Created);
+        assert!(content.starts_with("#!/bin/sh\n"));
+        assert!(content.contains(REFRESH_FILE));
+    }
+
+    #[test]
+    fn augments_existing_shell_hook() {
+        let (content, outcome) = merge_hook(Some("#!/bin/bash\necho hi")).unwrap();
+        assert_eq!(outcome, Outcome::Augmented);
+        assert!(content.starts_with("#!/bin/bash\n# >>> git-status-watch"));
+        assert!(content.ends_with("<<<\necho hi"));
+    }
+
+    #[test]
+    fn snippet_runs_before_trailing_exit() {
+        let (content, outcome) = merge_hook(Some("#!/bin/sh\necho hi\nexit 0\n")).unwrap();
+        assert_eq!(outcome, Outcome::Augmented);
+        let snippet_at = content.find(BEGIN).unwrap();
+        assert!(snippet_at < content.find("exit 0").unwrap());
+    }
+
+    #[test]
+    fn accepts_env_shell_shebang() {
+        let (_, outcome) = merge_hook(Some("#!/usr/bin/env bash\necho hi\n")).unwrap();
+        assert_eq!(outcome, Outcome::Augmented);
+    }
+
+    #[test]
+    fn reinstall_is_idempotent() {
+        let (first, _) = merge_hook(Some("#!/bin/sh\necho before\n")).unwrap();
+        let first = format!("{}echo after\n", first);
+        let (second, outcome) = merge_hook(Some(&first)).unwrap();
+        assert_eq!(outcome, Outcome::Updated);
+        assert_eq!(second, first);
+        assert_eq!(second.matches(BEGIN).count(), 1);
+    }
+
+    #[test]
+    fn skips_non_shell_hook() {
+        let result = merge_hook(Some("#!/usr/bin/env python3\nprint('hi')\n"));
+        assert_eq!(result.unwrap_err(), "not a shell script");
+        let result = merge_hook(Some("#!/usr/bin/env fish\necho hi\n"));
+        assert_eq!(result.unwrap_err(), "not a shell script");
+    }
+}
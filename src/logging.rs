@@ -0,0 +1,57 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+use tracing::level_filters::LevelFilter;
+
+use crate::cli::LogLevel;
+
+/// Install the global tracing subscriber. Logs go to `log_file` (appended)
+/// when given, otherwise to stderr so stdout stays machine-readable. Asking
+/// for a log file without a level logs at info rather than not at all.
+pub fn init(level: Option<LogLevel>, log_file: Option<&Path>) {
+    let level = level.unwrap_or(if log_file.is_some() {
+        LogLevel::Info
+    } else {
+        LogLevel::Off
+    });
+    let filter = level_filter(level);
+    if filter == LevelFilter::OFF {
+        return;
+    }
+
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(filter)
+        .with_target(false)
+        .with_ansi(false);
+
+    match log_file {
+        Some(path) => {
+            let file = fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .unwrap_or_else(|e| {
+                    eprintln!(
+                        "git-status-watch: cannot open log file {}: {}",
+                        path.display(),
+                        e
+                    );
+                    std::process::exit(1);
+                });
+            builder.with_writer(Mutex::new(file)).init();
+        }
+        None => builder.with_writer(std::io::stderr).init(),
+    }
+}
+
+fn level_filter(level: LogLevel) -> LevelFilter {
+    match level {
+        LogLevel::Off => LevelFilter::OFF,
+        LogLevel::Error => LevelFilter::ERROR,
+        LogLevel::Warn => LevelFilter::WARN,
+        LogLevel::Info => LevelFilter::INFO,
+        LogLevel::Debug => LevelFilter::DEBUG,
+        LogLevel::Trace => LevelFilter::TRACE,
+    }
+}
@@ -1,5 +1,11 @@
+mod bench;
 mod cli;
+mod doctor;
 mod format;
+mod hooks;
+mod logging;
+mod osc;
+mod state;
 mod status;
 mod types;
 mod watcher;
@@ -9,81 +15,314 @@ use std::fs;
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use std::process;
+use std::time::Duration;
 
 fn main() {
     reset_sigpipe();
 
     let cli = cli::Cli::parse();
+    logging::init(cli.log_level, cli.log_file.as_deref());
+
+    if let Some(command) = &cli.command {
+        run_command(&cli, command);
+        return;
+    }
+
     let repo_root = resolve_repo_root(cli.path.as_deref());
     let (git_dir, common_dir) = status::resolve_git_dirs(&repo_root);
 
-    let state_dir = default_state_dir();
+    let state_dir = state::default_dir();
     fs::create_dir_all(&state_dir).expect("git-status-watch: cannot create state dir");
-    let state_path = state_file_path(&state_dir, &repo_root);
+    let state_path = state::file_path(&state_dir, &repo_root);
+
+    // --header requires --output, so this prints once ahead of the first row
+    if let Some(output) = cli.output.filter(|_| cli.header) {
+        if print_stdout(&format::format_header(output.delimiter())).is_err() {
+            return;
+        }
+    }
 
     if cli.once {
-        // Fast path: if a watcher is maintaining the state file, just read it
-        if is_watched(&state_path) {
-            if let Some(status) = read_state_file(&state_path) {
-                let output = format_output(&status, cli.format.as_deref());
-                let _ = print_stdout(&output);
-                return;
+        let options = cli.status_options();
+        // Fast path: if a watcher is maintaining the state file, just read it,
+        // as long as the watcher fills every field we were asked for
+        let watched = state::is_watched(&state_path);
+        if watched {
+            match state::read_with_options(&state_path) {
+                Some((mut status, leader)) if leader.covers(options) => {
+                    tracing::debug!("once: served from state file");
+                    options.mask(&mut status);
+                    let output = format::render(&status, cli.style(&repo_root));
+                    let _ = print_stdout(&output);
+                    if let Some(mut osc) = cli.osc.then(osc::Osc::open).flatten() {
+                        osc.emit(&status);
+                    }
+                    return;
+                }
+                Some(_) => tracing::debug!("once: watcher lacks requested fields, computing"),
+                None => {}
             }
         }
-        let status = status::compute_status(&repo_root, &git_dir, &common_dir);
-        let output = format_output(&status, cli.format.as_deref());
-        write_state_file(&state_path, &status);
+        let status = status::compute_status(
+            &repo_root,
+            &git_dir,
+            &common_dir,
+            options,
+            &mut status::HeadCache::default(),
+        );
+        let output = format::render(&status, cli.style(&repo_root));
+        if !watched {
+            state::write(&state_path, &status, options);
+        }
         let _ = print_stdout(&output);
+        if let Some(mut osc) = cli.osc.then(osc::Osc::open).flatten() {
+            osc.emit(&status);
+        }
         return;
     }
 
-    // Watch mode: coordinate via lock file
-    let _lock = try_lock(&state_path);
+    // Watch mode: coordinate via lock file. SIGUSR1 is blocked first: once
+    // the lock names this PID, a `notify` would otherwise kill us before the
+    // leader gets to install the handler
+    let (tx, rx) = std::sync::mpsc::channel();
+    watcher::forward_refresh_signal(tx.clone());
+    let _lock = state::claim(&state_path);
 
     if _lock.is_none() {
-        run_follower(&state_path, cli.format.as_deref(), cli.always_print);
+        tracing::info!(state = %state_path.display(), "another watcher holds the lock, following");
+        let osc = cli.osc.then(osc::Osc::open).flatten();
+        let style = cli.style(&repo_root);
+        let options = cli.status_options();
+        if run_follower(&state_path, style, cli.always_print, osc, options) {
+            // The leader was started without some of our options; compute
+            // our own status, leaving the shared state file to the leader
+            tracing::info!("leader lacks requested fields, watching independently");
+            run_leader(&repo_root, &git_dir, &common_dir, None, &cli, (tx, rx));
+        }
     } else {
-        run_leader(&repo_root, &git_dir, &common_dir, &state_path, &cli);
+        tracing::info!(repo = %repo_root.display(), "acquired lock, leading");
+        run_leader(
+            &repo_root,
+            &git_dir,
+            &common_dir,
+            Some(&state_path),
+            &cli,
+            (tx, rx),
+        );
     }
 }
 
+fn run_command(cli: &cli::Cli, command: &cli::Command) {
+    match command {
+        cli::Command::Doctor { path } => {
+            let repo_root = resolve_repo_root(path.as_deref());
+            let state_dir = state::default_dir();
+            let state_path = state::file_path(&state_dir, &repo_root);
+            let backend = watcher::resolve_backend(cli.backend, &repo_root, cli.poll_interval);
+            if !doctor::run(&repo_root, &state_dir, &state_path, backend) {
+                process::exit(1);
+            }
+        }
+        cli::Command::Bench { path, iterations } => {
+            let repo_root = resolve_repo_root(path.as_deref());
+            let (git_dir, common_dir) = status::resolve_git_dirs(&repo_root);
+            bench::run(&repo_root, &git_dir, &common_dir, *iterations);
+        }
+        cli::Command::InstallHooks { path } => {
+            let repo_root = resolve_repo_root(path.as_deref());
+            match hooks::install(&repo_root) {
+                Ok(results) => {
+                    for (name, outcome) in results {
+                        match outcome {
+                            hooks::Outcome::Created => println!("created   {}", name),
+                            hooks::Outcome::Augmented => println!("augmented {}", name),
+                            hooks::Outcome::Updated => println!("updated   {}", name),
+                            hooks::Outcome::Skipped(reason) => {
+                                println!("skipped   {} ({})", name, reason)
+                            }
+                        }
+                    }
+                }
+                Err(e) => {
+                    eprintln!("git-status-watch: cannot install hooks: {}", e);
+                    process::exit(1);
+                }
+            }
+        }
+        cli::Command::List {
+            format,
+            output,
+            header,
+        } => {
+            let watchers = state::active_watchers(&state::default_dir());
+            if let Some(output) = output {
+                let delimiter = output.delimiter();
+                if *header {
+                    println!("{}", format::format_header(delimiter));
+                }
+                for w in watchers {
+                    if let Some(status) = w.status {
+                        println!("{}", format::format_row(&status, &w.repo, delimiter));
+                    }
+                }
+                return;
+            }
+            let style = format
+                .as_deref()
+                .map_or(format::Style::Json, format::Style::Template);
+            if watchers.is_empty() {
+                return;
+            }
+            println!("{:<8}{:<6}{:<40} STATUS", "PID", "AGE", "REPO");
+            for w in watchers {
+                let pid = w.pid.map(|p| p.to_string()).unwrap_or_else(|| "-".into());
+                let age = w.age.map(format::format_age).unwrap_or_else(|| "-".into());
+                let status = w
+                    .status
+                    .map(|s| format::render(&s, style))
+                    .unwrap_or_else(|| "-".into());
+                println!("{:<8}{:<6}{:<40} {}", pid, age, w.repo.display(), status);
+            }
+        }
+        cli::Command::Read { path, format } => {
+            let start = path.clone().unwrap_or_else(|| {
+                std::env::current_dir().expect("cannot determine current directory")
+            });
+            let Some(repo_root) = status::find_repo_root(&start) else {
+                eprintln!(
+                    "git-status-watch: not a git repository: {}",
+                    start.display()
+                );
+                process::exit(1);
+            };
+            let state_path = state::file_path(&state::default_dir(), &repo_root);
+            let Some(status) = state::read(&state_path) else {
+                eprintln!(
+                    "git-status-watch: no cached status for {}",
+                    repo_root.display()
+                );
+                process::exit(1);
+            };
+            let stale = !state::is_watched(&state_path);
+            let output = match format {
+                Some(t) => format::format_cached_custom(&status, t, stale),
+                None => format::format_cached_json(&status, stale),
+            };
+            let _ = print_stdout(&output);
+        }
+        cli::Command::Notify { path } => {
+            let repo_root = resolve_repo_root(path.as_deref());
+            let state_path = state::file_path(&state::default_dir(), &repo_root);
+            let Some(pid) = state::leader_pid(&state_path) else {
+                eprintln!(
+                    "git-status-watch: no watcher running for {}",
+                    repo_root.display()
+                );
+                process::exit(1);
+            };
+            if let Err(e) = watcher::send_refresh(pid) {
+                eprintln!(
+                    "git-status-watch: cannot notify watcher (pid {}): {}",
+                    pid, e
+                );
+                process::exit(1);
+            }
+        }
+    }
+}
+
+/// Compute and print status on every change, publishing it to `state_path`
+/// when this process holds the lock. `events` already carries SIGUSR1
+/// refreshes from `forward_refresh_signal`.
 fn run_leader(
     repo_root: &Path,
     git_dir: &Path,
     common_dir: &Path,
-    state_path: &Path,
+    state_path: Option<&Path>,
     cli: &cli::Cli,
+    events: (
+        std::sync::mpsc::Sender<watcher::WatchEvent>,
+        std::sync::mpsc::Receiver<watcher::WatchEvent>,
+    ),
 ) {
+    let backend = watcher::resolve_backend(cli.backend, repo_root, cli.poll_interval);
+    let (tx, rx) = events;
+
     let stdout = io::stdout();
     let mut out = stdout.lock();
 
-    let status = status::compute_status(repo_root, git_dir, common_dir);
-    let output = format_output(&status, cli.format.as_deref());
-    write_state_file(state_path, &status);
+    let mut osc = cli.osc.then(osc::Osc::open).flatten();
+    let options = cli.status_options();
+    let mut cache = status::HeadCache::default();
+    let status = status::compute_status(repo_root, git_dir, common_dir, options, &mut cache);
+    let output = format::render(&status, cli.style(repo_root));
+    if let Some(state_path) = state_path {
+        state::write(state_path, &status, options);
+    }
     if write_line(&mut out, &output).is_err() {
         return;
     }
+    if let Some(osc) = &mut osc {
+        osc.emit(&status);
+    }
     let mut last_status = status;
 
-    let (rx, _debouncer) = watcher::start_watcher(repo_root, cli.debounce_ms);
+    let debounce = watcher::Debounce {
+        worktree: Duration::from_millis(cli.debounce_ms),
+        git: Duration::from_millis(cli.git_debounce_ms.unwrap_or(cli.debounce_ms)),
+    };
+    let _watcher = watcher::start_watcher(repo_root, backend, debounce, tx).unwrap_or_else(|e| {
+        eprintln!("git-status-watch: {}", e);
+        process::exit(1);
+    });
+    let mut budget = (backend == watcher::Backend::Inotify).then(watcher::BudgetMonitor::default);
+    if let Some(budget) = &mut budget {
+        budget.poll();
+    }
 
     loop {
         match rx.recv() {
-            Ok(watcher::WatchEvent::Changed) => {
-                let status = status::compute_status(repo_root, git_dir, common_dir);
+            Ok(event @ (watcher::WatchEvent::Changed | watcher::WatchEvent::Refresh)) => {
+                if matches!(event, watcher::WatchEvent::Refresh) {
+                    tracing::info!("refresh requested");
+                }
+                // Anything queued while the previous recompute ran is
+                // covered by this one
+                while let Ok(queued) = rx.try_recv() {
+                    if let watcher::WatchEvent::Error(e) = queued {
+                        tracing::error!(error = %e, "watcher error");
+                        eprintln!("git-status-watch: watcher error: {}", e);
+                    }
+                }
+                let status =
+                    status::compute_status(repo_root, git_dir, common_dir, options, &mut cache);
+                // New directories may have added watches
+                if let Some(budget) = &mut budget {
+                    budget.poll();
+                }
                 if cli.always_print || status != last_status {
-                    let output = format_output(&status, cli.format.as_deref());
-                    write_state_file(state_path, &status);
+                    tracing::debug!("status changed, emitting");
+                    let output = format::render(&status, cli.style(repo_root));
+                    if let Some(state_path) = state_path {
+                        state::write(state_path, &status, options);
+                    }
                     if write_line(&mut out, &output).is_err() {
                         return;
                     }
+                    if let Some(osc) = &mut osc {
+                        osc.emit(&status);
+                    }
                     last_status = status;
+                } else {
+                    tracing::debug!("status unchanged, suppressing output");
                 }
             }
             Ok(watcher::WatchEvent::Error(e)) => {
+                tracing::error!(error = %e, "watcher error");
                 eprintln!("git-status-watch: watcher error: {}", e);
             }
             Err(_) => {
+                tracing::error!("watcher channel closed");
                 eprintln!("git-status-watch: watcher channel closed");
                 process::exit(1);
             }
@@ -91,18 +330,33 @@ fn run_leader(
     }
 }
 
-fn run_follower(state_path: &Path, template: Option<&str>, always_print: bool) {
+/// Print the leader's status from the state file whenever it changes.
+/// Returns true if the leader's status lacks fields `options` asks for, so
+/// the caller has to compute its own.
+fn run_follower(
+    state_path: &Path,
+    style: format::Style,
+    always_print: bool,
+    mut osc: Option<osc::Osc>,
+    options: status::Options,
+) -> bool {
     use std::sync::mpsc;
-    use std::time::Duration;
 
     let stdout = io::stdout();
     let mut out = stdout.lock();
     let mut last_status: Option<types::GitStatus> = None;
 
-    if let Some(status) = read_state_file(state_path) {
-        let output = format_output(&status, template);
+    if let Some((mut status, leader)) = state::read_with_options(state_path) {
+        if !leader.covers(options) {
+            return true;
+        }
+        options.mask(&mut status);
+        let output = format::render(&status, style);
         if write_line(&mut out, &output).is_err() {
-            return;
+            return false;
+        }
+        if let Some(osc) = &mut osc {
+            osc.emit(&status);
         }
         last_status = Some(status);
     }
@@ -138,28 +392,29 @@ fn run_follower(state_path: &Path, template: Option<&str>, always_print: bool) {
     loop {
         match rx.recv() {
             Ok(()) => {
-                if let Some(status) = read_state_file(state_path) {
+                tracing::debug!("state file changed");
+                if let Some((mut status, leader)) = state::read_with_options(state_path) {
+                    if !leader.covers(options) {
+                        return true;
+                    }
+                    options.mask(&mut status);
                     if always_print || last_status.as_ref() != Some(&status) {
-                        let output = format_output(&status, template);
+                        let output = format::render(&status, style);
                         if write_line(&mut out, &output).is_err() {
-                            return;
+                            return false;
+                        }
+                        if let Some(osc) = &mut osc {
+                            osc.emit(&status);
                         }
                         last_status = Some(status);
                     }
                 }
             }
-            Err(_) => return,
+            Err(_) => return false,
         }
     }
 }
 
-fn default_state_dir() -> PathBuf {
-    let base = std::env::var_os("XDG_RUNTIME_DIR")
-        .map(PathBuf::from)
-        .unwrap_or_else(std::env::temp_dir);
-    base.join("git-status-watch")
-}
-
 fn resolve_repo_root(path: Option<&Path>) -> PathBuf {
     let dir = path
         .map(|p| p.to_path_buf())
@@ -185,36 +440,6 @@ fn resolve_repo_root(path: Option<&Path>) -> PathBuf {
     PathBuf::from(s.trim())
 }
 
-fn state_file_path(state_dir: &Path, repo_root: &Path) -> PathBuf {
-    let encoded = repo_root.to_string_lossy().replace('/', "%2F");
-    state_dir.join(encoded)
-}
-
-fn write_state_file(path: &Path, status: &types::GitStatus) {
-    let json = serde_json::to_string(status).unwrap();
-    let tmp = path.with_extension("tmp");
-    if fs::write(&tmp, json.as_bytes()).is_ok() {
-        let _ = fs::rename(&tmp, path);
-    }
-}
-
-fn read_state_file(path: &Path) -> Option<types::GitStatus> {
-    let content = fs::read_to_string(path).ok()?;
-    serde_json::from_str(&content).ok()
-}
-
-/// Check if another watcher holds the lock for this state file.
-fn is_watched(state_path: &Path) -> bool {
-    try_lock(state_path).is_none()
-}
-
-fn format_output(status: &types::GitStatus, template: Option<&str>) -> String {
-    match template {
-        Some(t) => format::format_custom(status, t),
-        None => format::format_json(status),
-    }
-}
-
 fn print_stdout(s: &str) -> io::Result<()> {
     let stdout = io::stdout();
     let mut out = stdout.lock();
@@ -227,29 +452,6 @@ fn write_line(out: &mut io::StdoutLock, s: &str) -> io::Result<()> {
     out.flush()
 }
 
-#[cfg(unix)]
-fn try_lock(state_path: &Path) -> Option<fs::File> {
-    use std::os::unix::io::AsRawFd;
-    let lock_path = state_path.with_extension("lock");
-    let file = fs::OpenOptions::new()
-        .create(true)
-        .truncate(true)
-        .write(true)
-        .open(&lock_path)
-        .ok()?;
-    let fd = file.as_raw_fd();
-    if unsafe { libc::flock(fd, libc::LOCK_EX | libc::LOCK_NB) } == 0 {
-        Some(file)
-    } else {
-        None
-    }
-}
-
-#[cfg(not(unix))]
-fn try_lock(_state_path: &Path) -> Option<fs::File> {
-    None
-}
-
 #[cfg(unix)]
 fn reset_sigpipe() {
     unsafe {
@@ -0,0 +1,116 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+
+use crate::types::GitStatus;
+
+/// `--osc` sink: publishes status as terminal user variables
+/// (`OSC 1337 SetUserVar`, understood by iTerm2, WezTerm, and kitty) on the
+/// controlling terminal, so stdout stays clean for whatever consumes it.
+pub struct Osc {
+    tty: File,
+    tmux: bool,
+}
+
+impl Osc {
+    /// None when there is no controlling terminal (e.g. under a status bar).
+    pub fn open() -> Option<Osc> {
+        match OpenOptions::new().write(true).open("/dev/tty") {
+            Ok(tty) => Some(Osc {
+                tty,
+                tmux: std::env::var_os("TMUX").is_some(),
+            }),
+            Err(e) => {
+                tracing::warn!(error = %e, "--osc: cannot open terminal, disabled");
+                None
+            }
+        }
+    }
+
+    pub fn emit(&mut self, status: &GitStatus) {
+        let seq = user_vars(status, self.tmux);
+        if let Err(e) = self.tty.write_all(seq.as_bytes()) {
+            tracing::debug!(error = %e, "--osc: write failed");
+        }
+    }
+}
+
+/// `gstat_branch`, `gstat_dirty`, `gstat_state`, `gstat_ahead`, and
+/// `gstat_behind`, each base64-encoded as the protocol requires. Inside
+/// tmux every sequence is wrapped for passthrough.
+fn user_vars(status: &GitStatus, tmux: bool) -> String {
+    let vars = [
+        ("gstat_branch", status.branch.clone()),
+        ("gstat_dirty", status.dirty.to_string()),
+        ("gstat_state", status.state_label()),
+        ("gstat_ahead", status.ahead.to_string()),
+        ("gstat_behind", status.behind.to_string()),
+    ];
+    let mut out = String::new();
+    for (name, value) in vars {
+        let seq = format!(
+            "\x1b]1337;SetUserVar={}={}\x07",
+            name,
+            base64(value.as_bytes())
+        );
+        if tmux {
+            out.push_str("\x1bPtmux;");
+            out.push_str(&seq.replace('\x1b', "\x1b\x1b"));
+            out.push_str("\x1b\\");
+        } else {
+            out.push_str(&seq);
+        }
+    }
+    out
+}
+
+fn base64(input: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_padding() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"m"), "bQ==");
+        assert_eq!(base64(b"ma"), "bWE=");
+        assert_eq!(base64(b"main"), "bWFpbg==");
+        assert_eq!(base64("feat/ü".as_bytes()), "ZmVhdC/DvA==");
+    }
+
+    #[test]
+    fn user_var_sequences() {
+        let status: GitStatus = serde_json::from_str(
+            r#"{"branch":"main","staged":0,"modified":1,"untracked":0,"conflicted":0,
+            "ahead":0,"behind":0,"stash":0,"state":"clean","dirty":true}"#,
+        )
+        .unwrap();
+        let plain = user_vars(&status, false);
+        assert!(plain.starts_with("\x1b]1337;SetUserVar=gstat_branch=bWFpbg==\x07"));
+        assert!(plain.contains("SetUserVar=gstat_dirty=dHJ1ZQ==\x07"));
+        assert_eq!(plain.matches("\x1b]1337;").count(), 5);
+
+        let wrapped = user_vars(&status, true);
+        assert!(wrapped.starts_with("\x1bPtmux;\x1b\x1b]1337;SetUserVar=gstat_branch="));
+        assert_eq!(wrapped.matches("\x1b\\").count(), 5);
+    }
+}
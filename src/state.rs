@@ -0,0 +1,244 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::format;
+use crate::status::Options;
+use crate::types::GitStatus;
+
+pub fn default_dir() -> PathBuf {
+    let base = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir);
+    base.join("git-status-watch")
+}
+
+pub fn file_path(state_dir: &Path, repo_root: &Path) -> PathBuf {
+    let encoded = repo_root.to_string_lossy().replace('/', "%2F");
+    state_dir.join(encoded)
+}
+
+/// Recover the repository root a state file name was encoded from.
+pub fn repo_from_file_name(name: &str) -> PathBuf {
+    PathBuf::from(name.replace("%2F", "/"))
+}
+
+/// Derive a companion file (lock, tmp) by appending a suffix. Encoded repo
+/// paths may contain dots, so `with_extension` would clobber part of the name.
+pub fn sibling(state_path: &Path, suffix: &str) -> PathBuf {
+    let mut name = state_path.as_os_str().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// State file contents: the extended status plus the options it was
+/// computed with, so readers can tell which optional fields are filled.
+#[derive(Serialize)]
+struct StateOut<'a> {
+    #[serde(flatten)]
+    status: format::Extended<'a>,
+    options: Options,
+}
+
+#[derive(Deserialize)]
+struct StateIn {
+    #[serde(flatten)]
+    status: GitStatus,
+    #[serde(default)]
+    options: Options,
+}
+
+pub fn write(path: &Path, status: &GitStatus, options: Options) {
+    let json = serde_json::to_string(&StateOut {
+        status: format::Extended::new(status),
+        options,
+    })
+    .unwrap();
+    let tmp = sibling(path, "tmp");
+    if fs::write(&tmp, json.as_bytes()).is_ok() {
+        let _ = fs::rename(&tmp, path);
+    }
+}
+
+/// The status and the options the writer computed it with.
+pub fn read_with_options(path: &Path) -> Option<(GitStatus, Options)> {
+    let content = fs::read_to_string(path).ok()?;
+    let state: StateIn = serde_json::from_str(&content).ok()?;
+    Some((state.status, state.options))
+}
+
+pub fn read(path: &Path) -> Option<GitStatus> {
+    read_with_options(path).map(|(status, _)| status)
+}
+
+/// Check if another watcher holds the lock for this state file.
+pub fn is_watched(state_path: &Path) -> bool {
+    try_lock(state_path).is_none()
+}
+
+/// Take the lock as leader and record our PID in it so other processes
+/// can signal us.
+pub fn claim(state_path: &Path) -> Option<fs::File> {
+    use std::io::Write;
+    let mut file = try_lock(state_path)?;
+    let _ = file.set_len(0);
+    let _ = write!(file, "{}", std::process::id());
+    Some(file)
+}
+
+/// PID of the live leader for this state file, if any. The PID recorded
+/// in the lock file is only trusted while that process still holds the
+/// lock: it may belong to a leader that has exited (and been reused since),
+/// and the lock may be held only briefly by a `--once` or `read` probe.
+pub fn leader_pid(state_path: &Path) -> Option<u32> {
+    let lock_path = sibling(state_path, "lock");
+    let pid = fs::read_to_string(&lock_path).ok()?.trim().parse().ok()?;
+    holds_lock(pid, state_path, &lock_path).then_some(pid)
+}
+
+/// Whether `pid` holds the flock on `lock_path`, from `/proc/locks`.
+#[cfg(target_os = "linux")]
+fn holds_lock(pid: u32, _state_path: &Path, lock_path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    let Ok(meta) = fs::metadata(lock_path) else {
+        return false;
+    };
+    fs::read_to_string("/proc/locks")
+        .map(|locks| flock_holders(&locks, meta.ino()).contains(&pid))
+        .unwrap_or(false)
+}
+
+/// Without `/proc/locks`, settle for: someone holds the lock and `pid` is
+/// alive.
+#[cfg(all(unix, not(target_os = "linux")))]
+fn holds_lock(pid: u32, state_path: &Path, _lock_path: &Path) -> bool {
+    is_watched(state_path) && unsafe { libc::kill(pid as libc::pid_t, 0) } == 0
+}
+
+#[cfg(not(unix))]
+fn holds_lock(_pid: u32, _state_path: &Path, _lock_path: &Path) -> bool {
+    false
+}
+
+/// PIDs holding a granted flock on inode `ino`. Lines look like
+/// `1: FLOCK  ADVISORY  WRITE 4242 fd:01:1234 0 EOF`; blocked waiters are
+/// marked `1: -> FLOCK ...` and skipped.
+#[cfg(target_os = "linux")]
+fn flock_holders(locks: &str, ino: u64) -> Vec<u32> {
+    locks
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.get(1) != Some(&"FLOCK") {
+                return None;
+            }
+            let file_ino: u64 = fields.get(5)?.rsplit(':').next()?.parse().ok()?;
+            if file_ino != ino {
+                return None;
+            }
+            fields.get(4)?.parse().ok()
+        })
+        .collect()
+}
+
+/// A repo with a live leader, as seen from the state directory.
+pub struct Watcher {
+    pub repo: PathBuf,
+    pub pid: Option<u32>,
+    /// Time since the leader last wrote the state file.
+    pub age: Option<Duration>,
+    pub status: Option<GitStatus>,
+}
+
+/// Every repo whose lock is currently held, sorted by path.
+pub fn active_watchers(state_dir: &Path) -> Vec<Watcher> {
+    let Ok(entries) = fs::read_dir(state_dir) else {
+        return Vec::new();
+    };
+    let mut watchers = Vec::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some(base) = name.to_str().and_then(|n| n.strip_suffix(".lock")) else {
+            continue;
+        };
+        let state_path = state_dir.join(base);
+        if !is_watched(&state_path) {
+            continue;
+        }
+        let age = fs::metadata(&state_path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| SystemTime::now().duration_since(t).ok());
+        watchers.push(Watcher {
+            repo: repo_from_file_name(base),
+            pid: leader_pid(&state_path),
+            age,
+            status: read(&state_path),
+        });
+    }
+    watchers.sort_by(|a, b| a.repo.cmp(&b.repo));
+    watchers
+}
+
+#[cfg(unix)]
+pub fn try_lock(state_path: &Path) -> Option<fs::File> {
+    use std::os::unix::io::AsRawFd;
+    let lock_path = sibling(state_path, "lock");
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .ok()?;
+    let fd = file.as_raw_fd();
+    if unsafe { libc::flock(fd, libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+        Some(file)
+    } else {
+        None
+    }
+}
+
+#[cfg(not(unix))]
+pub fn try_lock(_state_path: &Path) -> Option<fs::File> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_name_round_trip() {
+        let dir = Path::new("/run/gsw");
+        let path = file_path(dir, Path::new("/home/me/my.repo"));
+        let name = path.file_name().unwrap().to_str().unwrap();
+        assert_eq!(name, "%2Fhome%2Fme%2Fmy.repo");
+        assert_eq!(repo_from_file_name(name), PathBuf::from("/home/me/my.repo"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn parse_proc_locks() {
+        let locks = "\
+1: FLOCK  ADVISORY  WRITE 4242 fd:01:1234 0 EOF
+1: -> FLOCK  ADVISORY  WRITE 5151 fd:01:1234 0 EOF
+2: POSIX  ADVISORY  WRITE 6060 fd:01:1234 0 EOF
+3: FLOCK  ADVISORY  WRITE 7070 fd:01:9999 0 EOF
+";
+        assert_eq!(flock_holders(locks, 1234), vec![4242]);
+        assert_eq!(flock_holders(locks, 9999), vec![7070]);
+        assert!(flock_holders(locks, 1).is_empty());
+    }
+
+    #[test]
+    fn sibling_keeps_dotted_names() {
+        let path = Path::new("/run/gsw/%2Fhome%2Fme%2Fmy.repo");
+        assert_eq!(
+            sibling(path, "lock"),
+            PathBuf::from("/run/gsw/%2Fhome%2Fme%2Fmy.repo.lock")
+        );
+    }
+}
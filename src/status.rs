@@ -1,7 +1,10 @@
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
+use std::time::{Instant, SystemTime};
 
-use crate::types::{GitStatus, OperationState};
+use serde::{Deserialize, Serialize};
+
+use crate::types::{ConflictCounts, GitStatus, OperationState, StateFlags};
 
 /// Resolve the worktree-aware git directory and common directory.
 /// For normal repos both are `repo_root/.git`.
@@ -34,56 +37,368 @@ pub fn resolve_git_dirs(repo_root: &Path) -> (PathBuf, PathBuf) {
     (dot_git.clone(), dot_git)
 }
 
-pub fn compute_status(repo_root: &Path, git_dir: &Path, common_dir: &Path) -> GitStatus {
-    let porcelain = run_git(repo_root, &[
-        "-c",
-        "gc.auto=0",
-        "--no-optional-locks",
-        "status",
-        "--porcelain=v2",
-        "--branch",
-    ]);
-    let (branch, ahead, behind, staged, modified, untracked, conflicted) =
-        parse_porcelain_v2(&porcelain);
+/// Find the worktree root by walking up from `start` to the nearest `.git`
+/// entry, without spawning git.
+pub fn find_repo_root(start: &Path) -> Option<PathBuf> {
+    let start = start.canonicalize().ok()?;
+    start
+        .ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+/// Optional fields that cost extra git calls. All off by default.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Options {
+    /// Fill `commit_subject` and `commit_author`.
+    pub commit_info: bool,
+    /// Fill `tag`, `commits_since_tag`, and `version` from `git describe`.
+    pub describe: bool,
+    /// Fill `hidden_changes` from `git ls-files -v`.
+    pub hidden_changes: bool,
+}
+
+impl Options {
+    /// Whether a status computed with these options fills every field
+    /// `wanted` asks for.
+    pub fn covers(self, wanted: Options) -> bool {
+        (self.commit_info || !wanted.commit_info)
+            && (self.describe || !wanted.describe)
+            && (self.hidden_changes || !wanted.hidden_changes)
+    }
+
+    /// Clear the fields these options did not ask for, so a status computed
+    /// by a leader with more options renders the same as one computed here.
+    pub fn mask(self, status: &mut GitStatus) {
+        if !self.commit_info {
+            status.commit_subject.clear();
+            status.commit_author.clear();
+        }
+        if !self.describe {
+            status.tag.clear();
+            status.commits_since_tag = 0;
+            status.version.clear();
+        }
+        if !self.hidden_changes {
+            status.hidden_changes = 0;
+        }
+    }
+}
+
+/// `git describe --tags --long` split into its parts.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Describe {
+    pub tag: String,
+    pub distance: u32,
+    pub hash: String,
+}
+
+impl Describe {
+    /// Build-script friendly version: the tag without a leading `v`, plus
+    /// `-<distance>-g<hash>` when HEAD is past it (`1.2.3`, `1.2.3-14-gabcdef0`).
+    /// Empty when there is no tag.
+    pub fn version(&self) -> String {
+        if self.tag.is_empty() {
+            return String::new();
+        }
+        let base = self.tag.strip_prefix('v').unwrap_or(&self.tag);
+        if self.distance == 0 {
+            base.to_string()
+        } else {
+            format!("{}-{}-g{}", base, self.distance, self.hash)
+        }
+    }
+}
+
+/// Modification times of the places tags live (the newest directory under
+/// `refs/tags`, and `packed-refs`), used to notice new or deleted tags
+/// without re-running describe.
+type TagsStamp = (Option<SystemTime>, Option<SystemTime>);
+
+/// Lookups that depend only on HEAD (and, for describe, tags), reused by
+/// the leader until those refs change.
+#[derive(Debug, Default)]
+pub struct HeadCache {
+    oid: String,
+    tags: TagsStamp,
+    commit: Option<(String, String)>,
+    describe: Option<Describe>,
+    /// Upstream remote name and the host parsed from its URL, with the
+    /// upstream and config mtime they were read at. Kept across HEAD moves;
+    /// `git remote set-url` and `git branch -u` both rewrite the config.
+    remote: Option<(RemoteStamp, String, String)>,
+    /// `hidden_changes` with the index and config mtimes it was counted at.
+    /// Kept across HEAD moves; only an index or config write can change it.
+    hidden: Option<(HiddenStamp, u32)>,
+    /// `lfs_pending` with the HEAD oid, upstream oid, and index mtime it was
+    /// counted at.
+    lfs: Option<(LfsStamp, u32)>,
+}
+
+/// Upstream shorthand and the repo config's modification time.
+type RemoteStamp = (String, Option<SystemTime>);
+
+/// Modification times of the index and the repo config.
+type HiddenStamp = (Option<SystemTime>, Option<SystemTime>);
+
+/// HEAD oid, upstream oid, and index mtime.
+type LfsStamp = (String, Option<String>, Option<SystemTime>);
+
+impl HeadCache {
+    /// Drop everything cached for a previous HEAD or tag set.
+    fn sync(&mut self, oid: &str, tags: TagsStamp) {
+        if self.oid != oid {
+            tracing::debug!(oid, "HEAD moved, invalidating cache");
+            *self = HeadCache {
+                oid: oid.to_string(),
+                tags,
+                remote: self.remote.take(),
+                hidden: self.hidden.take(),
+                lfs: self.lfs.take(),
+                ..Default::default()
+            };
+        } else if self.tags != tags {
+            tracing::debug!("tags changed, invalidating describe");
+            self.tags = tags;
+            self.describe = None;
+        }
+    }
+
+    fn describe(&mut self, repo_root: &Path) -> Describe {
+        self.describe
+            .get_or_insert_with(|| {
+                let out = run_git(repo_root, &["describe", "--tags", "--long", "--abbrev=7"]);
+                parse_describe(&out).unwrap_or_default()
+            })
+            .clone()
+    }
+
+    /// Remote name and host for `branch`, which tracks `upstream`.
+    fn remote(
+        &mut self,
+        repo_root: &Path,
+        common_dir: &Path,
+        branch: &str,
+        upstream: &str,
+    ) -> (String, String) {
+        let stamp = (
+            upstream.to_string(),
+            std::fs::metadata(common_dir.join("config"))
+                .and_then(|m| m.modified())
+                .ok(),
+        );
+        if let Some((cached, name, host)) = &self.remote {
+            if *cached == stamp {
+                return (name.clone(), host.clone());
+            }
+        }
+        let name = upstream_remote(repo_root, branch);
+        let host = if name.is_empty() {
+            String::new()
+        } else {
+            // get-url applies `url.<base>.insteadOf`, so `gh:o/r` aliases
+            // resolve to the real host
+            parse_remote_host(&run_git(repo_root, &["remote", "get-url", &name]))
+        };
+        self.remote = Some((stamp, name.clone(), host.clone()));
+        (name, host)
+    }
+
+    fn hidden_changes(&mut self, repo_root: &Path, git_dir: &Path, common_dir: &Path) -> u32 {
+        let mtime = |p: &Path| std::fs::metadata(p).and_then(|m| m.modified()).ok();
+        let stamp = (
+            mtime(&git_dir.join("index")),
+            mtime(&common_dir.join("config")),
+        );
+        match self.hidden {
+            Some((cached, count)) if cached == stamp => count,
+            _ => {
+                let count = count_hidden(repo_root);
+                self.hidden = Some((stamp, count));
+                count
+            }
+        }
+    }
 
+    fn lfs_pending(
+        &mut self,
+        repo_root: &Path,
+        git_dir: &Path,
+        common_dir: &Path,
+        upstream: Option<&str>,
+    ) -> u32 {
+        let stamp = (
+            self.oid.clone(),
+            upstream.and_then(|u| read_ref(common_dir, &format!("refs/remotes/{}", u))),
+            std::fs::metadata(git_dir.join("index"))
+                .and_then(|m| m.modified())
+                .ok(),
+        );
+        match &self.lfs {
+            Some((cached, count)) if *cached == stamp => *count,
+            _ => {
+                let count = lfs_pending(repo_root, upstream);
+                self.lfs = Some((stamp, count));
+                count
+            }
+        }
+    }
+
+    fn commit_info(&mut self, repo_root: &Path) -> (String, String) {
+        self.commit
+            .get_or_insert_with(|| {
+                let out = run_git(repo_root, &["log", "-1", "--format=%s%x00%an", "HEAD"]);
+                parse_commit_info(&out)
+            })
+            .clone()
+    }
+}
+
+pub fn compute_status(
+    repo_root: &Path,
+    git_dir: &Path,
+    common_dir: &Path,
+    options: Options,
+    cache: &mut HeadCache,
+) -> GitStatus {
+    let start = Instant::now();
+    let porcelain = run_porcelain(repo_root);
+    let git_elapsed = start.elapsed();
+    let p = parse_porcelain_v2(&porcelain);
+
+    let hidden_changes = if options.hidden_changes {
+        cache.hidden_changes(repo_root, git_dir, common_dir)
+    } else {
+        0
+    };
     let stash = stash_count(common_dir);
     let state = detect_operation_state(git_dir);
+    let state_flags = StateFlags {
+        paused: state == OperationState::Rebase && p.conflicted == 0 && is_paused(git_dir),
+        conflicted: state != OperationState::Clean && p.conflicted > 0,
+    };
+    let merging = if state == OperationState::Merge {
+        merge_source(repo_root, git_dir)
+    } else {
+        String::new()
+    };
+    let op_remaining = match state {
+        OperationState::CherryPick | OperationState::Revert => {
+            std::fs::read_to_string(git_dir.join("sequencer/todo"))
+                .map(|todo| count_todo(&todo))
+                .unwrap_or(0)
+        }
+        _ => 0,
+    };
+
+    cache.sync(&p.oid, tags_stamp(common_dir));
+    let (remote, remote_host) = match &p.upstream {
+        Some(upstream) => cache.remote(repo_root, common_dir, &p.branch, upstream),
+        None => (String::new(), String::new()),
+    };
+    let lfs_pending = if uses_lfs(common_dir) {
+        cache.lfs_pending(repo_root, git_dir, common_dir, p.upstream.as_deref())
+    } else {
+        0
+    };
+    let (commit_subject, commit_author) = if options.commit_info && p.oid != "(initial)" {
+        cache.commit_info(repo_root)
+    } else {
+        (String::new(), String::new())
+    };
+    let describe = if options.describe && p.oid != "(initial)" {
+        cache.describe(repo_root)
+    } else {
+        Describe::default()
+    };
+    tracing::debug!(
+        git_us = git_elapsed.as_micros() as u64,
+        total_us = start.elapsed().as_micros() as u64,
+        "computed status"
+    );
+
+    let changes = p.staged + p.modified + p.untracked + p.conflicted + p.typechange;
 
     GitStatus {
-        branch,
-        staged,
-        modified,
-        untracked,
-        conflicted,
-        ahead,
-        behind,
+        branch: p.branch,
+        staged: p.staged,
+        modified: p.modified,
+        untracked: p.untracked,
+        conflicted: p.conflicted,
+        typechange: p.typechange,
+        hidden_changes,
+        lfs_pending,
+        ahead: p.ahead,
+        behind: p.behind,
+        remote,
+        remote_host,
         stash,
         state,
+        state_flags,
+        merging,
+        op_remaining,
+        commit_subject,
+        commit_author,
+        version: describe.version(),
+        tag: describe.tag,
+        commits_since_tag: describe.distance,
+        dirty: changes > 0,
+        changes,
+        conflicts: p.conflicts,
     }
 }
 
-fn run_git(repo_root: &Path, args: &[&str]) -> String {
+/// Raw `git status --porcelain=v2 --branch` output for the repo.
+pub fn run_porcelain(repo_root: &Path) -> String {
+    run_git(repo_root, &[
+        "-c",
+        "gc.auto=0",
+        "--no-optional-locks",
+        "status",
+        "--porcelain=v2",
+        "--branch",
+    ])
+}
+
+pub fn run_git(repo_root: &Path, args: &[&str]) -> String {
     Command::new("git")
         .args(args)
         .current_dir(repo_root)
+        // Never block on a credential prompt
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
         .stderr(Stdio::null())
         .output()
         .map(|o| {
             String::from_utf8(o.stdout)
                 .unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned())
         })
-        .unwrap_or_default()
+        .unwrap_or_else(|e| {
+            tracing::warn!(error = %e, ?args, "failed to run git");
+            String::new()
+        })
+}
+
+/// Counts extracted from `git status --porcelain=v2 --branch`.
+#[derive(Debug, Default)]
+pub struct Porcelain {
+    pub branch: String,
+    /// HEAD commit id, or `(initial)` before the first commit.
+    pub oid: String,
+    pub upstream: Option<String>,
+    pub ahead: u32,
+    pub behind: u32,
+    pub staged: u32,
+    pub modified: u32,
+    pub untracked: u32,
+    pub conflicted: u32,
+    pub typechange: u32,
+    pub conflicts: ConflictCounts,
 }
 
-fn parse_porcelain_v2(output: &str) -> (String, u32, u32, u32, u32, u32, u32) {
-    let mut branch = String::new();
-    let mut oid = "";
-    let mut ahead: u32 = 0;
-    let mut behind: u32 = 0;
-    let mut staged: u32 = 0;
-    let mut modified: u32 = 0;
-    let mut untracked: u32 = 0;
-    let mut conflicted: u32 = 0;
+pub fn parse_porcelain_v2(output: &str) -> Porcelain {
+    let mut p = Porcelain::default();
 
     for line in output.lines() {
         let bytes = line.as_bytes();
@@ -93,54 +408,159 @@ fn parse_porcelain_v2(output: &str) -> (String, u32, u32, u32, u32, u32, u32) {
         match bytes[0] {
             b'#' => {
                 if let Some(rest) = line.strip_prefix("# branch.head ") {
-                    branch = rest.to_string();
+                    p.branch = rest.to_string();
                 } else if let Some(rest) = line.strip_prefix("# branch.ab ") {
                     for part in rest.split_ascii_whitespace() {
                         if let Some(n) = part.strip_prefix('+') {
-                            ahead = n.parse().unwrap_or(0);
+                            p.ahead = n.parse().unwrap_or(0);
                         } else if let Some(n) = part.strip_prefix('-') {
-                            behind = n.parse().unwrap_or(0);
+                            p.behind = n.parse().unwrap_or(0);
                         }
                     }
                 } else if let Some(rest) = line.strip_prefix("# branch.oid ") {
-                    oid = rest;
+                    p.oid = rest.to_string();
+                } else if let Some(rest) = line.strip_prefix("# branch.upstream ") {
+                    p.upstream = Some(rest.to_string());
+                }
+            }
+            b'u' => {
+                p.conflicted += 1;
+                if bytes.len() >= 4 {
+                    match &bytes[2..4] {
+                        b"UU" => p.conflicts.both_modified += 1,
+                        b"DU" => p.conflicts.deleted_by_us += 1,
+                        b"UD" => p.conflicts.deleted_by_them += 1,
+                        b"AA" => p.conflicts.both_added += 1,
+                        _ => {}
+                    }
                 }
             }
-            b'u' => conflicted += 1,
             b'1' | b'2' if bytes.len() >= 4 && bytes[1] == b' ' => {
-                if bytes[2] != b'.' {
-                    staged += 1;
+                // Type changes are reported under `typechange` only, so they
+                // are not counted twice in `changes`.
+                if !matches!(bytes[2], b'.' | b'T') {
+                    p.staged += 1;
                 }
-                if bytes[3] != b'.' {
-                    modified += 1;
+                if !matches!(bytes[3], b'.' | b'T') {
+                    p.modified += 1;
+                }
+                if bytes[2] == b'T' || bytes[3] == b'T' {
+                    p.typechange += 1;
                 }
             }
-            b'?' => untracked += 1,
+            b'?' => p.untracked += 1,
             _ => {}
         }
     }
 
-    if branch == "(detached)" {
-        branch = if oid.len() >= 7 {
-            oid[..7].to_string()
-        } else if !oid.is_empty() {
-            oid.to_string()
+    if p.branch == "(detached)" {
+        p.branch = if p.oid.len() >= 7 {
+            p.oid[..7].to_string()
+        } else if !p.oid.is_empty() {
+            p.oid.clone()
         } else {
             "HEAD".to_string()
         };
     }
 
-    (branch, ahead, behind, staged, modified, untracked, conflicted)
+    p
+}
+
+/// Run `git ls-files -v` and count the hidden entries. In a sparse
+/// checkout, skip-worktree marks the paths outside the sparse set, so only
+/// assume-unchanged entries count there.
+pub fn count_hidden(repo_root: &Path) -> u32 {
+    let sparse = run_git(repo_root, &["config", "--bool", "core.sparseCheckout"]).trim() == "true";
+    hidden_count(&run_git(repo_root, &["ls-files", "-v"]), sparse)
+}
+
+/// Count `git ls-files -v` entries tagged skip-worktree (`S`, unless
+/// `sparse`) or assume-unchanged (lowercase tag).
+pub fn hidden_count(ls_files: &str, sparse: bool) -> u32 {
+    ls_files
+        .lines()
+        .filter(|line| {
+            line.as_bytes()
+                .first()
+                .is_some_and(|&tag| (tag == b'S' && !sparse) || tag.is_ascii_lowercase())
+        })
+        .count() as u32
+}
+
+/// Remote `branch` tracks, from `branch.<name>.remote`. The upstream
+/// shorthand can't be split on `/`: remote names may contain one, and a
+/// branch tracking a local branch (remote `.`) has no remote at all.
+pub fn upstream_remote(repo_root: &Path, branch: &str) -> String {
+    let key = format!("branch.{}.remote", branch);
+    match run_git(repo_root, &["config", "--get", &key]).trim() {
+        "." => String::new(),
+        name => name.to_string(),
+    }
+}
+
+/// LFS creates its object store on first use, so this skips the `git lfs`
+/// subprocesses in the common case.
+pub fn uses_lfs(common_dir: &Path) -> bool {
+    common_dir.join("lfs").is_dir()
+}
+
+/// LFS files changed between `upstream` and HEAD, plus files still present
+/// only as pointers in the worktree. Each path counts once even when it is
+/// both. Both lists come from local refs: asking the LFS server what it
+/// has would mean a network round trip (and maybe a credential prompt) on
+/// every recompute.
+pub fn lfs_pending(repo_root: &Path, upstream: Option<&str>) -> u32 {
+    let unpushed = match upstream {
+        Some(upstream) => run_git(repo_root, &["lfs", "ls-files", upstream, "HEAD"]),
+        None => String::new(),
+    };
+    lfs_pending_count(&unpushed, &run_git(repo_root, &["lfs", "ls-files"]))
+}
+
+/// Union of every path in `unpushed` (`git lfs ls-files <upstream> HEAD`)
+/// and the `ls_files` entries marked `-` (pointer not smudged) rather than
+/// `*` (object checked out). Both use the `<oid> <*|-> <path>` format.
+pub fn lfs_pending_count(unpushed: &str, ls_files: &str) -> u32 {
+    let unpushed = lfs_entries(unpushed).map(|(_, path)| path);
+    let pointers = lfs_entries(ls_files)
+        .filter(|&(mark, _)| mark == "-")
+        .map(|(_, path)| path);
+    unpushed
+        .chain(pointers)
+        .collect::<std::collections::HashSet<_>>()
+        .len() as u32
 }
 
-fn stash_count(common_dir: &Path) -> u32 {
+/// `(mark, path)` for each `<oid> <mark> <path>` line.
+fn lfs_entries(out: &str) -> impl Iterator<Item = (&str, &str)> {
+    out.lines().filter_map(|line| {
+        let mut parts = line.splitn(3, ' ');
+        let _oid = parts.next()?;
+        Some((parts.next()?, parts.next()?))
+    })
+}
+
+/// Resolve a full ref name to its oid from the loose ref file or
+/// `packed-refs`, without spawning git.
+fn read_ref(common_dir: &Path, name: &str) -> Option<String> {
+    if let Ok(oid) = std::fs::read_to_string(common_dir.join(name)) {
+        return Some(oid.trim().to_string());
+    }
+    let packed = std::fs::read_to_string(common_dir.join("packed-refs")).ok()?;
+    packed.lines().find_map(|line| {
+        let (oid, refname) = line.split_once(' ')?;
+        (refname == name).then(|| oid.to_string())
+    })
+}
+
+pub fn stash_count(common_dir: &Path) -> u32 {
     match std::fs::read(common_dir.join("logs/refs/stash")) {
         Ok(bytes) => bytes.iter().filter(|&&b| b == b'\n').count() as u32,
         Err(_) => 0,
     }
 }
 
-fn detect_operation_state(git_dir: &Path) -> OperationState {
+pub fn detect_operation_state(git_dir: &Path) -> OperationState {
     if git_dir.join("MERGE_HEAD").exists() {
         OperationState::Merge
     } else if git_dir.join("rebase-merge").exists() || git_dir.join("rebase-apply").exists() {
@@ -156,10 +576,141 @@ fn detect_operation_state(git_dir: &Path) -> OperationState {
     }
 }
 
+/// Name of what is being merged: the refs quoted in MERGE_MSG, falling back
+/// to `git name-rev` on MERGE_HEAD, then its short hash.
+fn merge_source(repo_root: &Path, git_dir: &Path) -> String {
+    if let Ok(msg) = std::fs::read_to_string(git_dir.join("MERGE_MSG")) {
+        if let Some(name) = parse_merge_msg(&msg) {
+            return name;
+        }
+    }
+    let Ok(head) = std::fs::read_to_string(git_dir.join("MERGE_HEAD")) else {
+        return String::new();
+    };
+    let oid = head.lines().next().unwrap_or("").trim();
+    let name = run_git(
+        repo_root,
+        &["name-rev", "--name-only", "--no-undefined", oid],
+    );
+    match name.trim() {
+        "" => oid.get(..7).unwrap_or(oid).to_string(),
+        name => name.to_string(),
+    }
+}
+
+/// Extract the quoted names from a default merge message such as
+/// `Merge branch 'feature' into main` or `Merge branches 'a' and 'b'`.
+fn parse_merge_msg(msg: &str) -> Option<String> {
+    let first = msg.lines().next()?;
+    if !first.starts_with("Merge ") {
+        return None;
+    }
+    let names: Vec<&str> = first.split('\'').skip(1).step_by(2).collect();
+    if names.is_empty() {
+        None
+    } else {
+        Some(names.join(", "))
+    }
+}
+
+/// Split `git log -1 --format=%s%x00%an` output into subject and author.
+fn parse_commit_info(out: &str) -> (String, String) {
+    let out = out.trim_end_matches('\n');
+    match out.split_once('\0') {
+        Some((subject, author)) => (subject.to_string(), author.to_string()),
+        None => (out.to_string(), String::new()),
+    }
+}
+
+/// Host part of a remote URL: `https://host/...`, `ssh://user@host:22/...`,
+/// or scp-like `user@host:path`. Empty for local paths and `file://`.
+fn parse_remote_host(url: &str) -> String {
+    let url = url.trim();
+    let authority = if let Some((scheme, rest)) = url.split_once("://") {
+        if scheme == "file" {
+            return String::new();
+        }
+        let authority = rest.split('/').next().unwrap_or("");
+        let host = authority.rsplit_once('@').map_or(authority, |(_, h)| h);
+        match host.strip_prefix('[') {
+            Some(v6) => v6.split(']').next().unwrap_or(""),
+            None => host.split(':').next().unwrap_or(""),
+        }
+    } else {
+        match url.split_once(':') {
+            // A slash before the colon makes it a local path, not scp syntax
+            Some((host, _)) if !host.contains('/') => {
+                host.rsplit_once('@').map_or(host, |(_, h)| h)
+            }
+            _ => "",
+        }
+    };
+    authority.to_string()
+}
+
+fn tags_stamp(common_dir: &Path) -> TagsStamp {
+    let mtime = |p: &Path| std::fs::metadata(p).and_then(|m| m.modified()).ok();
+    (
+        newest_dir_mtime(&common_dir.join("refs/tags")),
+        mtime(&common_dir.join("packed-refs")),
+    )
+}
+
+/// Latest mtime of `dir` and every directory below it. Adding or removing
+/// a tag like `v1/rc2` only touches `refs/tags/v1`.
+fn newest_dir_mtime(dir: &Path) -> Option<SystemTime> {
+    let mut newest = std::fs::metadata(dir).and_then(|m| m.modified()).ok()?;
+    for entry in std::fs::read_dir(dir).ok()?.flatten() {
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            if let Some(sub) = newest_dir_mtime(&entry.path()) {
+                newest = newest.max(sub);
+            }
+        }
+    }
+    Some(newest)
+}
+
+/// Parse `v1.2.3-14-gabcdef0` from the right, since tags may contain `-`.
+pub fn parse_describe(out: &str) -> Option<Describe> {
+    let mut parts = out.trim().rsplitn(3, '-');
+    let hash = parts.next()?.strip_prefix('g')?;
+    let distance = parts.next()?.parse().ok()?;
+    let tag = parts.next()?;
+    Some(Describe {
+        tag: tag.to_string(),
+        distance,
+        hash: hash.to_string(),
+    })
+}
+
+/// Count commands in a sequencer todo list, skipping comments and blanks.
+fn count_todo(todo: &str) -> u32 {
+    todo.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .count() as u32
+}
+
+/// An interactive rebase stopped at an `edit` leaves `amend` behind until
+/// the user continues.
+fn is_paused(git_dir: &Path) -> bool {
+    git_dir.join("rebase-merge/amend").exists()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn find_repo_root_walks_up() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().canonicalize().unwrap();
+        std::fs::create_dir(root.join(".git")).unwrap();
+        std::fs::create_dir_all(root.join("a/b")).unwrap();
+        assert_eq!(find_repo_root(&root.join("a/b")), Some(root.clone()));
+        assert_eq!(find_repo_root(&root), Some(root));
+    }
+
     #[test]
     fn parse_clean_repo() {
         let output = "\
@@ -168,15 +719,14 @@ mod tests {
 # branch.upstream origin/main
 # branch.ab +0 -0
 ";
-        let (branch, ahead, behind, staged, modified, untracked, conflicted) =
-            parse_porcelain_v2(output);
-        assert_eq!(branch, "main");
-        assert_eq!(ahead, 0);
-        assert_eq!(behind, 0);
-        assert_eq!(staged, 0);
-        assert_eq!(modified, 0);
-        assert_eq!(untracked, 0);
-        assert_eq!(conflicted, 0);
+        let p = parse_porcelain_v2(output);
+        assert_eq!(p.branch, "main");
+        assert_eq!(p.ahead, 0);
+        assert_eq!(p.behind, 0);
+        assert_eq!(p.staged, 0);
+        assert_eq!(p.modified, 0);
+        assert_eq!(p.untracked, 0);
+        assert_eq!(p.conflicted, 0);
     }
 
     #[test]
@@ -193,15 +743,14 @@ mod tests {
 ? another-new.txt
 u UU N... 100755 100755 100755 100755 abc123 def456 ghi789 conflict.rs
 ";
-        let (branch, ahead, behind, staged, modified, untracked, conflicted) =
-            parse_porcelain_v2(output);
-        assert_eq!(branch, "feature/test");
-        assert_eq!(ahead, 3);
-        assert_eq!(behind, 1);
-        assert_eq!(staged, 2); // M. and MM
-        assert_eq!(modified, 2); // .M and MM
-        assert_eq!(untracked, 2);
-        assert_eq!(conflicted, 1);
+        let p = parse_porcelain_v2(output);
+        assert_eq!(p.branch, "feature/test");
+        assert_eq!(p.ahead, 3);
+        assert_eq!(p.behind, 1);
+        assert_eq!(p.staged, 2); // M. and MM
+        assert_eq!(p.modified, 2); // .M and MM
+        assert_eq!(p.untracked, 2);
+        assert_eq!(p.conflicted, 1);
     }
 
     #[test]
@@ -210,8 +759,8 @@ u UU N... 100755 100755 100755 100755 abc123 def456 ghi789 conflict.rs
 # branch.oid abc1234567890def
 # branch.head (detached)
 ";
-        let (branch, _, _, _, _, _, _) = parse_porcelain_v2(output);
-        assert_eq!(branch, "abc1234");
+        let p = parse_porcelain_v2(output);
+        assert_eq!(p.branch, "abc1234");
     }
 
     #[test]
@@ -221,8 +770,229 @@ u UU N... 100755 100755 100755 100755 abc123 def456 ghi789 conflict.rs
 # branch.head main
 2 R. N... 100644 100644 100644 abc123 def456 R100 new.rs\told.rs
 ";
-        let (_, _, _, staged, modified, _, _) = parse_porcelain_v2(output);
-        assert_eq!(staged, 1);
-        assert_eq!(modified, 0);
+        let p = parse_porcelain_v2(output);
+        assert_eq!(p.staged, 1);
+        assert_eq!(p.modified, 0);
+    }
+
+    #[test]
+    fn options_cover_and_mask() {
+        let none = Options::default();
+        let commit = Options {
+            commit_info: true,
+            ..Options::default()
+        };
+        let describe = Options {
+            describe: true,
+            ..Options::default()
+        };
+        assert!(commit.covers(none));
+        assert!(!none.covers(commit));
+        assert!(!commit.covers(describe));
+        let hidden = Options {
+            hidden_changes: true,
+            ..Options::default()
+        };
+        assert!(!describe.covers(hidden));
+
+        let mut status: GitStatus = serde_json::from_str(
+            r#"{"branch":"main","staged":0,"modified":0,"untracked":0,"conflicted":0,
+            "ahead":0,"behind":0,"stash":0,"state":"clean","dirty":false,
+            "commit_subject":"initial","commit_author":"Test",
+            "tag":"v1","commits_since_tag":3,"version":"1-3-gabcdef0",
+            "hidden_changes":2}"#,
+        )
+        .unwrap();
+        commit.mask(&mut status);
+        assert_eq!(status.commit_subject, "initial");
+        assert_eq!(status.tag, "");
+        assert_eq!(status.commits_since_tag, 0);
+        assert_eq!(status.version, "");
+        assert_eq!(status.hidden_changes, 0);
+        none.mask(&mut status);
+        assert_eq!(status.commit_subject, "");
+        assert_eq!(status.commit_author, "");
+    }
+
+    #[test]
+    fn parse_typechange() {
+        let output = "\
+# branch.oid abc1234567890
+# branch.head main
+1 T. N... 100644 120000 120000 abc123 def456 staged-link
+1 .T N... 100644 100644 120000 abc123 abc123 worktree-link
+1 M. N... 100644 100644 100644 abc123 def456 plain.rs
+";
+        let p = parse_porcelain_v2(output);
+        assert_eq!(p.typechange, 2);
+        assert_eq!(p.staged, 1);
+        assert_eq!(p.modified, 0);
+    }
+
+    #[test]
+    fn count_hidden_entries() {
+        let output = "\
+H tracked.rs
+h assumed.rs
+S sparse/skipped.rs
+s both.rs
+M unmerged.rs
+";
+        assert_eq!(hidden_count(output, false), 3);
+        assert_eq!(hidden_count(output, true), 2);
+        assert_eq!(hidden_count("", false), 0);
+    }
+
+    #[test]
+    fn count_lfs_pending() {
+        let ls_files = "\
+4d7a214614 * assets/logo.png
+a5c3fe0b21 - assets/video.mp4
+0f1e2d3c4b - data/model bin.dat
+";
+        assert_eq!(lfs_pending_count("", ls_files), 2);
+        let unpushed = "\
+4d7a214614 * assets/logo.png
+a5c3fe0b21 - assets/video.mp4
+";
+        assert_eq!(lfs_pending_count(unpushed, ls_files), 3);
+        assert_eq!(lfs_pending_count("", ""), 0);
+    }
+
+    #[test]
+    fn parse_upstream() {
+        let p = parse_porcelain_v2("# branch.head main\n# branch.upstream origin/main\n");
+        assert_eq!(p.upstream.as_deref(), Some("origin/main"));
+        let p = parse_porcelain_v2("# branch.head main\n");
+        assert_eq!(p.upstream, None);
+    }
+
+    #[test]
+    fn merge_msg_names() {
+        assert_eq!(
+            parse_merge_msg("Merge branch 'feature/x'\n\n# Conflicts:\n").as_deref(),
+            Some("feature/x")
+        );
+        assert_eq!(
+            parse_merge_msg("Merge branch 'fix' into main\n").as_deref(),
+            Some("fix")
+        );
+        assert_eq!(
+            parse_merge_msg("Merge remote-tracking branch 'origin/main'").as_deref(),
+            Some("origin/main")
+        );
+        assert_eq!(
+            parse_merge_msg("Merge branches 'a' and 'b'").as_deref(),
+            Some("a, b")
+        );
+        assert_eq!(parse_merge_msg("custom message"), None);
+        assert_eq!(parse_merge_msg("Merge abc123"), None);
+    }
+
+    #[test]
+    fn sequencer_todo_count() {
+        let todo = "\
+pick abc1234 First
+pick def5678 Second
+
+# comment
+revert 0123abc Third
+";
+        assert_eq!(count_todo(todo), 3);
+        assert_eq!(count_todo(""), 0);
+    }
+
+    #[test]
+    fn commit_info_parsing() {
+        assert_eq!(
+            parse_commit_info("Fix the thing\0Ada Lovelace\n"),
+            ("Fix the thing".to_string(), "Ada Lovelace".to_string())
+        );
+        assert_eq!(parse_commit_info(""), (String::new(), String::new()));
+    }
+
+    #[test]
+    fn head_cache_invalidates_on_new_oid() {
+        let mut cache = HeadCache::default();
+        let stamp = (None, None);
+        cache.sync("aaa", stamp);
+        cache.commit = Some(("old".into(), "me".into()));
+        cache.describe = Some(Describe::default());
+        cache.sync("aaa", stamp);
+        assert!(cache.commit.is_some());
+        cache.sync("aaa", (Some(SystemTime::UNIX_EPOCH), None));
+        assert!(cache.commit.is_some());
+        assert!(cache.describe.is_none());
+        cache.sync("bbb", stamp);
+        assert!(cache.commit.is_none());
+    }
+
+    #[test]
+    fn remote_host_parsing() {
+        assert_eq!(
+            parse_remote_host("https://github.com/o/r.git\n"),
+            "github.com"
+        );
+        assert_eq!(parse_remote_host("git@gitlab.com:o/r.git"), "gitlab.com");
+        assert_eq!(
+            parse_remote_host("ssh://git@git.example.org:2222/o/r"),
+            "git.example.org"
+        );
+        assert_eq!(parse_remote_host("https://user:pw@host.io/r"), "host.io");
+        assert_eq!(parse_remote_host("ssh://[::1]:22/r"), "::1");
+        assert_eq!(parse_remote_host("/srv/git/r.git"), "");
+        assert_eq!(parse_remote_host("../r"), "");
+        assert_eq!(parse_remote_host("file:///srv/git/r.git"), "");
+        assert_eq!(parse_remote_host(""), "");
+    }
+
+    #[test]
+    fn describe_parsing() {
+        assert_eq!(
+            parse_describe("v2.3.1-14-gabcdef0\n"),
+            Some(Describe {
+                tag: "v2.3.1".into(),
+                distance: 14,
+                hash: "abcdef0".into(),
+            })
+        );
+        assert_eq!(
+            parse_describe("release-2024-01-0-g1234567").map(|d| d.tag),
+            Some("release-2024-01".into())
+        );
+        assert_eq!(parse_describe(""), None);
+    }
+
+    #[test]
+    fn describe_version() {
+        let mut d = parse_describe("v1.2.3-14-gabcdef0").unwrap();
+        assert_eq!(d.version(), "1.2.3-14-gabcdef0");
+        d.distance = 0;
+        assert_eq!(d.version(), "1.2.3");
+        assert_eq!(
+            parse_describe("release-7-3-g1234567").unwrap().version(),
+            "release-7-3-g1234567"
+        );
+        assert_eq!(Describe::default().version(), "");
+    }
+
+    #[test]
+    fn parse_conflict_types() {
+        let output = "\
+# branch.oid abc1234567890
+# branch.head main
+u UU N... 100644 100644 100644 100644 a b c both.rs
+u UU N... 100644 100644 100644 100644 a b c both2.rs
+u DU N... 000000 100644 100644 100644 a b c ours-deleted.rs
+u UD N... 100644 100644 000000 100644 a b c theirs-deleted.rs
+u AA N... 000000 100644 100644 100644 a b c added.rs
+u DD N... 100644 000000 000000 000000 a b c gone.rs
+";
+        let p = parse_porcelain_v2(output);
+        assert_eq!(p.conflicted, 6);
+        assert_eq!(p.conflicts.both_modified, 2);
+        assert_eq!(p.conflicts.deleted_by_us, 1);
+        assert_eq!(p.conflicts.deleted_by_them, 1);
+        assert_eq!(p.conflicts.both_added, 1);
     }
 }
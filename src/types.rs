@@ -8,10 +8,86 @@ pub struct GitStatus {
     pub modified: u32,
     pub untracked: u32,
     pub conflicted: u32,
+    /// Entries with a type change (file/symlink swap, submodule) in the
+    /// index or worktree. Not counted in `staged`/`modified`.
+    #[serde(default)]
+    pub typechange: u32,
+    /// Paths marked skip-worktree or assume-unchanged, whose local edits
+    /// `git status` does not report. In a sparse checkout skip-worktree just
+    /// means "outside the sparse set", so only assume-unchanged counts.
+    #[serde(default)]
+    pub hidden_changes: u32,
+    /// Git LFS files not yet pushed upstream or still checked out as
+    /// pointers. Always 0 in repos that don't use LFS.
+    #[serde(default)]
+    pub lfs_pending: u32,
     pub ahead: u32,
     pub behind: u32,
+    /// Remote of the upstream branch (e.g. `origin`), empty without one or
+    /// when the upstream is a local branch.
+    #[serde(default)]
+    pub remote: String,
+    /// Host from that remote's URL (e.g. `github.com`), empty for local
+    /// or file remotes.
+    #[serde(default)]
+    pub remote_host: String,
     pub stash: u32,
     pub state: OperationState,
+    #[serde(default)]
+    pub state_flags: StateFlags,
+    /// Branch or ref being merged while a merge is in progress, else empty.
+    #[serde(default)]
+    pub merging: String,
+    /// Commits left in a multi-commit cherry-pick or revert, including the
+    /// one currently stopped on.
+    #[serde(default)]
+    pub op_remaining: u32,
+    /// Subject of the HEAD commit (with `--commit-info`).
+    #[serde(default)]
+    pub commit_subject: String,
+    /// Author name of the HEAD commit (with `--commit-info`).
+    #[serde(default)]
+    pub commit_author: String,
+    /// Nearest tag reachable from HEAD (with `--describe`).
+    #[serde(default)]
+    pub tag: String,
+    /// Commits between that tag and HEAD (with `--describe`).
+    #[serde(default)]
+    pub commits_since_tag: u32,
+    /// Describe-derived version such as `1.2.3-14-gabcdef0` (with `--describe`).
+    #[serde(default)]
+    pub version: String,
+    /// Any staged, modified, untracked, conflicted, or type-changed entries.
+    #[serde(default)]
+    pub dirty: bool,
+    /// Sum of the staged, modified, untracked, conflicted, and typechange
+    /// counts.
+    #[serde(default)]
+    pub changes: u32,
+    /// Per-type conflict counts. Skipped by the plain serialization; see
+    /// `format::Extended` for `--extended` JSON and the state file.
+    #[serde(default, skip_serializing)]
+    pub conflicts: ConflictCounts,
+}
+
+/// Unmerged entries by XY code. Other unmerged codes (DD, AU, UA) only
+/// count toward `conflicted`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConflictCounts {
+    pub both_modified: u32,
+    pub deleted_by_us: u32,
+    pub deleted_by_them: u32,
+    pub both_added: u32,
+}
+
+/// Modifiers on top of the primary operation in `state`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateFlags {
+    /// Stopped on purpose mid-operation (e.g. an `edit` in an interactive
+    /// rebase) rather than on a conflict.
+    pub paused: bool,
+    /// The operation is blocked on unmerged paths.
+    pub conflicted: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -37,3 +113,20 @@ impl fmt::Display for OperationState {
         }
     }
 }
+
+impl GitStatus {
+    /// `{state}` rendering: the primary operation followed by any modifier
+    /// flags, e.g. `rebase:paused` or `merge:conflicted`. Empty when clean.
+    pub fn state_label(&self) -> String {
+        let mut label = self.state.to_string();
+        if self.state != OperationState::Clean {
+            if self.state_flags.paused {
+                label.push_str(":paused");
+            }
+            if self.state_flags.conflicted {
+                label.push_str(":conflicted");
+            }
+        }
+        label
+    }
+}
@@ -1,47 +1,585 @@
-use notify_debouncer_mini::new_debouncer;
+use notify::{RecursiveMode, Watcher};
+use std::fmt;
+use std::io::{BufRead, BufReader};
 use std::path::Path;
+use std::process::{Child, Command, Stdio};
 use std::sync::mpsc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+use crate::cli;
+use crate::hooks;
 
 pub enum WatchEvent {
     Changed,
+    /// Another process asked for an immediate recompute (`notify`).
+    Refresh,
     Error(String),
 }
 
+/// Debounce windows per event class. Ref/index writes under `.git` usually
+/// mean a branch switch or commit and should show up quickly; worktree
+/// events come in editor save storms and benefit from a longer window.
+#[derive(Debug, Clone, Copy)]
+pub struct Debounce {
+    pub worktree: Duration,
+    pub git: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EventClass {
+    Worktree,
+    Git,
+}
+
+/// Where raw events come from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Inotify,
+    FsEvents,
+    Kqueue,
+    /// ReadDirectoryChangesW, only ever chosen by `auto`.
+    Windows,
+    /// Rescan the tree every interval. Slower, but sees changes made by
+    /// other hosts on network mounts, which never raise native events.
+    Poll(Duration),
+    /// Paths streamed from `watchman-wait`.
+    Watchman,
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Backend::Inotify => write!(f, "inotify"),
+            Backend::FsEvents => write!(f, "fsevents"),
+            Backend::Kqueue => write!(f, "kqueue"),
+            Backend::Windows => write!(f, "windows"),
+            Backend::Poll(interval) => write!(f, "poll (every {}ms)", interval.as_millis()),
+            Backend::Watchman => write!(f, "watchman"),
+        }
+    }
+}
+
+/// The backend notify's `RecommendedWatcher` uses on this platform.
+fn native_backend() -> Backend {
+    if cfg!(any(target_os = "linux", target_os = "android")) {
+        Backend::Inotify
+    } else if cfg!(target_os = "macos") {
+        Backend::FsEvents
+    } else if cfg!(any(
+        target_os = "freebsd",
+        target_os = "openbsd",
+        target_os = "netbsd"
+    )) {
+        Backend::Kqueue
+    } else if cfg!(windows) {
+        Backend::Windows
+    } else {
+        Backend::Poll(Duration::from_secs(2))
+    }
+}
+
+/// Turn `--backend` into a concrete backend. `auto` picks the platform's
+/// native one, or polls when the repo sits on a network filesystem; naming
+/// the native one forces it even there.
+pub fn resolve_backend(
+    requested: cli::Backend,
+    repo_root: &Path,
+    poll_interval: Duration,
+) -> Backend {
+    match requested {
+        cli::Backend::Auto => match network_fs(repo_root) {
+            Some(fs) => {
+                tracing::info!(fs, "repo is on a network filesystem, polling");
+                Backend::Poll(poll_interval)
+            }
+            None => match native_backend() {
+                Backend::Poll(_) => Backend::Poll(poll_interval),
+                native => native,
+            },
+        },
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        cli::Backend::Inotify => forced_native(repo_root),
+        #[cfg(target_os = "macos")]
+        cli::Backend::Fsevents => forced_native(repo_root),
+        #[cfg(any(target_os = "freebsd", target_os = "openbsd", target_os = "netbsd"))]
+        cli::Backend::Kqueue => forced_native(repo_root),
+        cli::Backend::Poll => Backend::Poll(poll_interval),
+        cli::Backend::Watchman => Backend::Watchman,
+    }
+}
+
+/// The native backend, asked for by name. Native events miss changes made
+/// by other hosts on network mounts, so say so when that applies.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd"
+))]
+fn forced_native(repo_root: &Path) -> Backend {
+    let backend = native_backend();
+    if let Some(fs) = network_fs(repo_root) {
+        tracing::info!(fs, %backend, "repo is on a network filesystem, using native events anyway");
+    }
+    backend
+}
+
+/// Keeps the event source alive; dropping it stops watching.
+pub struct WatchHandle {
+    _notify: Option<Box<dyn Watcher + Send>>,
+    watchman: Option<Child>,
+}
+
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        if let Some(child) = &mut self.watchman {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
 pub fn start_watcher(
     repo_root: &Path,
-    debounce_ms: u64,
-) -> (mpsc::Receiver<WatchEvent>, notify_debouncer_mini::Debouncer<notify::RecommendedWatcher>) {
-    let (tx, rx) = mpsc::channel();
-    let repo_root_buf = repo_root.to_path_buf();
-
-    let mut debouncer = new_debouncer(
-        Duration::from_millis(debounce_ms),
-        move |result: Result<Vec<notify_debouncer_mini::DebouncedEvent>, notify::Error>| {
-            match result {
-                Ok(events) => {
-                    let dominated_events = events
-                        .iter()
-                        .any(|e| is_relevant(&e.path, &repo_root_buf));
-                    if dominated_events {
-                        let _ = tx.send(WatchEvent::Changed);
+    backend: Backend,
+    debounce: Debounce,
+    tx: mpsc::Sender<WatchEvent>,
+) -> Result<WatchHandle, String> {
+    let (raw_tx, raw_rx) = mpsc::channel();
+    tracing::debug!(%backend, "starting watcher");
+    let handle = match backend {
+        Backend::Watchman => WatchHandle {
+            _notify: None,
+            watchman: Some(start_watchman(repo_root, raw_tx)?),
+        },
+        _ => {
+            let mut watcher = notify_watcher(backend, raw_tx)
+                .map_err(|e| format!("cannot create {} watcher: {}", backend, e))?;
+            watcher
+                .watch(repo_root, RecursiveMode::Recursive)
+                .map_err(|e| match (&e.kind, watch_budget()) {
+                    (notify::ErrorKind::MaxFilesWatch, Some(b)) => format!(
+                        "cannot watch repository: inotify watch limit reached, {} of {} in use \
+                         (raise fs.inotify.max_user_watches or use --backend poll)",
+                        b.used, b.limit
+                    ),
+                    _ => format!("cannot watch repository: {}", e),
+                })?;
+            WatchHandle {
+                _notify: Some(watcher),
+                watchman: None,
+            }
+        }
+    };
+
+    let repo_root = repo_root.to_path_buf();
+    std::thread::spawn(move || coalesce(&repo_root, debounce, raw_rx, tx));
+
+    Ok(handle)
+}
+
+/// How often the leader re-reads inotify usage. New directories add
+/// watches, and other processes of the same user share the limit.
+const BUDGET_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Tells the user on stderr when the inotify budget gets close to running
+/// out, since that only shows up as stale output. Warns each time usage
+/// crosses into the last 20%, not on every check.
+#[derive(Debug, Default)]
+pub struct BudgetMonitor {
+    next_check: Option<Instant>,
+    near: bool,
+}
+
+impl BudgetMonitor {
+    /// Re-read usage unless the last check was under a minute ago.
+    pub fn poll(&mut self) {
+        let now = Instant::now();
+        if self.next_check.is_some_and(|next| now < next) {
+            return;
+        }
+        self.next_check = Some(now + BUDGET_CHECK_INTERVAL);
+        let Some(budget) = watch_budget() else {
+            return;
+        };
+        tracing::info!(used = budget.used, limit = budget.limit, "inotify watches");
+        if self.observe(budget) {
+            eprintln!(
+                "git-status-watch: {} of {} inotify watches in use; new directories may go \
+                 unwatched (raise fs.inotify.max_user_watches)",
+                budget.used, budget.limit
+            );
+        }
+    }
+
+    /// Record a reading; true when it is the first to be near the limit.
+    fn observe(&mut self, budget: WatchBudget) -> bool {
+        let was_near = std::mem::replace(&mut self.near, budget.is_near());
+        self.near && !was_near
+    }
+}
+
+type RawSender = mpsc::Sender<notify::Result<notify::Event>>;
+
+fn notify_watcher(backend: Backend, raw_tx: RawSender) -> notify::Result<Box<dyn Watcher + Send>> {
+    let config = notify::Config::default();
+    Ok(match backend {
+        Backend::Poll(interval) => Box::new(notify::PollWatcher::new(
+            raw_tx,
+            config.with_poll_interval(interval),
+        )?),
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        Backend::Inotify => Box::new(notify::INotifyWatcher::new(raw_tx, config)?),
+        #[cfg(target_os = "macos")]
+        Backend::FsEvents => Box::new(notify::FsEventWatcher::new(raw_tx, config)?),
+        #[cfg(any(target_os = "freebsd", target_os = "openbsd", target_os = "netbsd"))]
+        Backend::Kqueue => Box::new(notify::KqueueWatcher::new(raw_tx, config)?),
+        #[cfg(windows)]
+        Backend::Windows => Box::new(notify::ReadDirectoryChangesWatcher::new(raw_tx, config)?),
+        _ => {
+            return Err(notify::Error::generic(&format!(
+                "the {} backend is not available on this platform",
+                backend
+            )))
+        }
+    })
+}
+
+/// Run `watchman-wait` over the repo and forward each changed path it
+/// prints as a raw event, so it goes through the same filtering and
+/// debouncing as notify's.
+fn start_watchman(repo_root: &Path, raw_tx: RawSender) -> Result<Child, String> {
+    let mut child = Command::new("watchman-wait")
+        .args(["--max-events", "0", "."])
+        .current_dir(repo_root)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("the watchman backend needs watchman-wait on PATH: {}", e))?;
+    let stdout = child.stdout.take().expect("piped stdout");
+    let repo_root = repo_root.to_path_buf();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            let Ok(line) = line else { break };
+            let event = notify::Event::new(notify::EventKind::Any).add_path(repo_root.join(line));
+            if raw_tx.send(Ok(event)).is_err() {
+                return;
+            }
+        }
+        let _ = raw_tx.send(Err(notify::Error::generic("watchman-wait exited")));
+    });
+    Ok(child)
+}
+
+/// Relevant events in one batch past which it counts as a storm (a big
+/// checkout, a build writing into the tree).
+const STORM_EVENTS: usize = 256;
+
+/// Longest a storm may hold back a recompute, so a process that never
+/// stops writing can't freeze the status.
+const STORM_MAX_DELAY: Duration = Duration::from_secs(2);
+
+/// A batch of relevant events waiting for its recompute.
+#[derive(Debug)]
+struct Pending {
+    deadline: Instant,
+    started: Instant,
+    last: Instant,
+    events: usize,
+    /// Shortest window of any event in the batch.
+    quiet: Duration,
+}
+
+impl Pending {
+    fn new(now: Instant, window: Duration) -> Self {
+        Pending {
+            deadline: now + window,
+            started: now,
+            last: now,
+            events: 1,
+            quiet: window,
+        }
+    }
+
+    /// Later events can only pull the deadline earlier.
+    fn record(&mut self, now: Instant, window: Duration) {
+        self.events += 1;
+        self.last = now;
+        self.quiet = self.quiet.min(window);
+        self.deadline = self.deadline.min(now + window);
+    }
+
+    /// At the deadline: true to recompute now. A storm that is still
+    /// going instead pushes the deadline to one quiet window after its
+    /// latest event, so intermediate states are skipped and the recompute
+    /// that does run sees the final one.
+    fn fire(&mut self, now: Instant) -> bool {
+        let storming = self.events >= STORM_EVENTS
+            && now.duration_since(self.last) < self.quiet
+            && now.duration_since(self.started) < STORM_MAX_DELAY;
+        if storming {
+            self.deadline = (self.last + self.quiet).min(self.started + STORM_MAX_DELAY);
+        }
+        !storming
+    }
+}
+
+/// Batch raw events: the first relevant event opens a window sized by its
+/// class, later events can only pull the deadline earlier, and one
+/// `Changed` is sent when the window closes (or, for storms, once events
+/// stop; see `Pending::fire`).
+fn coalesce(
+    repo_root: &Path,
+    debounce: Debounce,
+    raw_rx: mpsc::Receiver<notify::Result<notify::Event>>,
+    tx: mpsc::Sender<WatchEvent>,
+) {
+    let mut pending: Option<Pending> = None;
+    loop {
+        let received = match &pending {
+            Some(p) => raw_rx.recv_timeout(p.deadline.saturating_duration_since(Instant::now())),
+            None => raw_rx
+                .recv()
+                .map_err(|_| mpsc::RecvTimeoutError::Disconnected),
+        };
+        match received {
+            // Opens and reads, including our own git calls, change nothing
+            Ok(Ok(event)) if event.kind.is_access() => {}
+            Ok(Ok(event)) => {
+                for path in &event.paths {
+                    let class = classify(path, repo_root);
+                    tracing::trace!(path = %path.display(), ?class, "event");
+                    let window = match class {
+                        Some(EventClass::Worktree) => debounce.worktree,
+                        Some(EventClass::Git) => debounce.git,
+                        None => continue,
+                    };
+                    let now = Instant::now();
+                    match &mut pending {
+                        Some(p) => p.record(now, window),
+                        None => {
+                            tracing::debug!(
+                                ?class,
+                                window_ms = window.as_millis() as u64,
+                                "scheduling recompute"
+                            );
+                            pending = Some(Pending::new(now, window));
+                        }
                     }
                 }
-                Err(e) => {
-                    let _ = tx.send(WatchEvent::Error(e.to_string()));
+            }
+            Ok(Err(e)) => {
+                if tx.send(WatchEvent::Error(e.to_string())).is_err() {
+                    return;
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                let Some(p) = &mut pending else { continue };
+                if !p.fire(Instant::now()) {
+                    tracing::trace!(events = p.events, "event storm, deferring recompute");
+                    continue;
+                }
+                tracing::debug!(events = p.events, "debounce window closed");
+                pending = None;
+                if tx.send(WatchEvent::Changed).is_err() {
+                    return;
                 }
             }
-        },
-    )
-    .expect("failed to create file watcher");
+            Err(mpsc::RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
 
-    use notify::RecursiveMode;
-    debouncer
-        .watcher()
-        .watch(repo_root, RecursiveMode::Recursive)
-        .expect("failed to watch repository");
+fn classify(path: &Path, repo_root: &Path) -> Option<EventClass> {
+    if !is_relevant(path, repo_root) {
+        return None;
+    }
+    let in_git = path
+        .strip_prefix(repo_root)
+        .ok()
+        .and_then(|r| r.components().next())
+        .is_some_and(|c| c.as_os_str() == ".git");
+    Some(if in_git {
+        EventClass::Git
+    } else {
+        EventClass::Worktree
+    })
+}
 
-    (rx, debouncer)
+/// inotify watches held against `fs.inotify.max_user_watches`, a limit
+/// shared by all of the user's processes. Past it, directories go
+/// unwatched and the status silently stops updating for them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchBudget {
+    pub used: u64,
+    pub limit: u64,
+}
+
+impl WatchBudget {
+    /// At or past 80% of the limit.
+    pub fn is_near(&self) -> bool {
+        self.used.saturating_mul(5) >= self.limit.saturating_mul(4)
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.used >= self.limit
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub fn inotify_limit() -> Option<u64> {
+    std::fs::read_to_string("/proc/sys/fs/inotify/max_user_watches")
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Watches held by all of the current user's processes, against the
+/// per-user limit.
+#[cfg(target_os = "linux")]
+pub fn watch_budget() -> Option<WatchBudget> {
+    use std::os::unix::fs::MetadataExt;
+
+    let limit = inotify_limit()?;
+    let uid = unsafe { libc::getuid() };
+    let used = std::fs::read_dir("/proc")
+        .ok()?
+        .flatten()
+        .filter(|e| e.metadata().is_ok_and(|m| m.uid() == uid))
+        .filter_map(|e| e.file_name().to_str()?.parse().ok())
+        .filter_map(process_watches)
+        .sum();
+    Some(WatchBudget { used, limit })
+}
+
+/// Watches held by process `pid`, from the `inotify wd:` lines the kernel
+/// lists in `/proc/<pid>/fdinfo` for each of its inotify fds.
+#[cfg(target_os = "linux")]
+pub fn process_watches(pid: u32) -> Option<u64> {
+    let fds = std::fs::read_dir(format!("/proc/{}/fd", pid)).ok()?;
+    let used = fds
+        .flatten()
+        .filter(|fd| {
+            std::fs::read_link(fd.path())
+                .is_ok_and(|target| target == Path::new("anon_inode:inotify"))
+        })
+        .filter_map(|fd| {
+            std::fs::read_to_string(format!("/proc/{}/fdinfo/{}", pid, fd.file_name().to_str()?))
+                .ok()
+        })
+        .map(|info| count_inotify_watches(&info))
+        .sum();
+    Some(used)
+}
+
+#[cfg(target_os = "linux")]
+fn count_inotify_watches(fdinfo: &str) -> u64 {
+    fdinfo
+        .lines()
+        .filter(|l| l.starts_with("inotify wd:"))
+        .count() as u64
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn watch_budget() -> Option<WatchBudget> {
+    None
+}
+
+/// Name of the network filesystem holding `path`, if it is on one.
+#[cfg(target_os = "linux")]
+pub fn network_fs(path: &Path) -> Option<&'static str> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut buf: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(c_path.as_ptr(), &mut buf) } != 0 {
+        return None;
+    }
+    // f_type's width varies by arch; the magic numbers all fit in 32 bits
+    fs_name(buf.f_type as u32)
+}
+
+/// Network filesystem named by a `statfs` magic number (from linux/magic.h).
+#[cfg(target_os = "linux")]
+fn fs_name(magic: u32) -> Option<&'static str> {
+    match magic {
+        0x6969 => Some("nfs"),
+        0x517B => Some("smb"),
+        0xFF53_4D42 => Some("cifs"),
+        0xFE53_4D42 => Some("smb2"),
+        0x0102_1997 => Some("9p"),
+        0x5346_414F => Some("afs"),
+        0x7375_7245 => Some("coda"),
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn network_fs(path: &Path) -> Option<&'static str> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut buf: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(c_path.as_ptr(), &mut buf) } != 0 {
+        return None;
+    }
+    let name = unsafe { std::ffi::CStr::from_ptr(buf.f_fstypename.as_ptr()) };
+    ["nfs", "smbfs", "afpfs", "webdav"]
+        .into_iter()
+        .find(|fs| name.to_bytes() == fs.as_bytes())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn network_fs(_path: &Path) -> Option<&'static str> {
+    None
+}
+
+/// Turn SIGUSR1 into `WatchEvent::Refresh`. Must run before any other
+/// thread is spawned so every thread inherits the blocked signal mask and
+/// only the dedicated `sigwait` thread receives it.
+#[cfg(unix)]
+pub fn forward_refresh_signal(tx: mpsc::Sender<WatchEvent>) {
+    let set = unsafe {
+        let mut set: libc::sigset_t = std::mem::zeroed();
+        libc::sigemptyset(&mut set);
+        libc::sigaddset(&mut set, libc::SIGUSR1);
+        libc::pthread_sigmask(libc::SIG_BLOCK, &set, std::ptr::null_mut());
+        set
+    };
+    std::thread::spawn(move || loop {
+        let mut sig = 0;
+        if unsafe { libc::sigwait(&set, &mut sig) } == 0
+            && sig == libc::SIGUSR1
+            && tx.send(WatchEvent::Refresh).is_err()
+        {
+            return;
+        }
+    });
+}
+
+#[cfg(not(unix))]
+pub fn forward_refresh_signal(_tx: mpsc::Sender<WatchEvent>) {}
+
+/// Ask the leader with `pid` to recompute immediately.
+#[cfg(unix)]
+pub fn send_refresh(pid: u32) -> std::io::Result<()> {
+    if unsafe { libc::kill(pid as libc::pid_t, libc::SIGUSR1) } == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(unix))]
+pub fn send_refresh(_pid: u32) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "signals are not supported on this platform",
+    ))
 }
 
 fn is_relevant(path: &Path, repo_root: &Path) -> bool {
@@ -67,6 +605,8 @@ fn is_relevant(path: &Path, repo_root: &Path) -> bool {
             s == "HEAD"
                 || s == "index"
                 || s == "refs"
+                || s == "packed-refs"
+                || s == "config"
                 || s == "MERGE_HEAD"
                 || s == "REBASE_HEAD"
                 || s == "CHERRY_PICK_HEAD"
@@ -74,6 +614,8 @@ fn is_relevant(path: &Path, repo_root: &Path) -> bool {
                 || s == "BISECT_LOG"
                 || s == "rebase-merge"
                 || s == "rebase-apply"
+                || s == "sequencer"
+                || s == hooks::REFRESH_FILE
         }
     }
 }
@@ -108,7 +650,7 @@ mod tests {
             &root
         ));
         assert!(!is_relevant(
-            &PathBuf::from("/repo/.git/config"),
+            &PathBuf::from("/repo/.git/config.lock"),
             &root
         ));
     }
@@ -130,6 +672,158 @@ mod tests {
             &PathBuf::from("/repo/.git/rebase-merge/done"),
             &root
         ));
+        assert!(is_relevant(
+            &PathBuf::from("/repo/.git/git-status-watch.refresh"),
+            &root
+        ));
+        assert!(is_relevant(
+            &PathBuf::from("/repo/.git/sequencer/todo"),
+            &root
+        ));
+        assert!(is_relevant(&PathBuf::from("/repo/.git/packed-refs"), &root));
+        assert!(is_relevant(&PathBuf::from("/repo/.git/config"), &root));
+    }
+
+    #[test]
+    fn classify_git_vs_worktree() {
+        let root = PathBuf::from("/repo");
+        assert_eq!(
+            classify(&PathBuf::from("/repo/.git/HEAD"), &root),
+            Some(EventClass::Git)
+        );
+        assert_eq!(
+            classify(&PathBuf::from("/repo/src/main.rs"), &root),
+            Some(EventClass::Worktree)
+        );
+        assert_eq!(
+            classify(&PathBuf::from("/repo/.git/objects/ab/cdef"), &root),
+            None
+        );
+    }
+
+    #[test]
+    fn storm_defers_until_quiet() {
+        let ms = Duration::from_millis;
+        let t0 = Instant::now();
+        let window = ms(75);
+
+        // A short burst fires at its deadline as before
+        let mut p = Pending::new(t0, window);
+        p.record(t0 + ms(10), window);
+        assert!(p.fire(t0 + ms(75)));
+
+        // A storm still running at the deadline waits for a quiet window
+        let mut p = Pending::new(t0, window);
+        for n in 1..STORM_EVENTS as u64 {
+            p.record(t0 + ms(n / 4), window);
+        }
+        let last = p.last;
+        assert!(!p.fire(t0 + ms(75)));
+        assert_eq!(p.deadline, last + window);
+        assert!(p.fire(last + window));
+
+        // ...but not forever
+        let mut p = Pending::new(t0, window);
+        for n in 1..=2000 {
+            p.record(t0 + ms(n), window);
+        }
+        assert!(!p.fire(t0 + ms(1990)));
+        assert_eq!(p.deadline, t0 + STORM_MAX_DELAY);
+        assert!(p.fire(t0 + STORM_MAX_DELAY));
+    }
+
+    #[test]
+    fn coalesce_ignores_access_events() {
+        use notify::event::{AccessKind, DataChange, ModifyKind};
+        use notify::{Event, EventKind};
+
+        let root = PathBuf::from("/repo");
+        let debounce = Debounce {
+            worktree: Duration::from_millis(10),
+            git: Duration::from_millis(10),
+        };
+        let (raw_tx, raw_rx) = mpsc::channel();
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || coalesce(&root, debounce, raw_rx, tx));
+
+        let head = PathBuf::from("/repo/.git/HEAD");
+        let access = Event::new(EventKind::Access(AccessKind::Any)).add_path(head.clone());
+        raw_tx.send(Ok(access)).unwrap();
+        assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+
+        let modify =
+            Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Any))).add_path(head);
+        raw_tx.send(Ok(modify)).unwrap();
+        assert!(matches!(
+            rx.recv_timeout(Duration::from_secs(1)),
+            Ok(WatchEvent::Changed)
+        ));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn backend_resolution() {
+        let tmp = std::env::temp_dir();
+        let secs = Duration::from_secs;
+        assert_eq!(
+            resolve_backend(cli::Backend::Auto, &tmp, secs(2)),
+            Backend::Inotify
+        );
+        assert_eq!(
+            resolve_backend(cli::Backend::Inotify, &tmp, secs(2)),
+            Backend::Inotify
+        );
+        assert_eq!(
+            resolve_backend(cli::Backend::Poll, &tmp, secs(1)),
+            Backend::Poll(secs(1))
+        );
+        assert_eq!(
+            resolve_backend(cli::Backend::Watchman, &tmp, secs(1)),
+            Backend::Watchman
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn network_fs_magic_numbers() {
+        assert_eq!(fs_name(0x6969), Some("nfs"));
+        assert_eq!(fs_name(0xFF53_4D42), Some("cifs"));
+        assert_eq!(fs_name(0xFE53_4D42), Some("smb2"));
+        assert_eq!(fs_name(0x0102_1997), Some("9p"));
+        // ext4, tmpfs, btrfs
+        assert_eq!(fs_name(0xEF53), None);
+        assert_eq!(fs_name(0x0102_1994), None);
+        assert_eq!(fs_name(0x9123_683E), None);
+    }
+
+    #[test]
+    fn watch_budget_thresholds() {
+        let budget = |used| WatchBudget { used, limit: 1000 };
+        assert!(!budget(799).is_near());
+        assert!(budget(800).is_near());
+        assert!(!budget(999).is_over());
+        assert!(budget(1000).is_over());
+    }
+
+    #[test]
+    fn budget_monitor_warns_on_crossing() {
+        let budget = |used| WatchBudget { used, limit: 1000 };
+        let mut monitor = BudgetMonitor::default();
+        assert!(!monitor.observe(budget(100)));
+        assert!(monitor.observe(budget(850)));
+        assert!(!monitor.observe(budget(900)));
+        assert!(!monitor.observe(budget(500)));
+        assert!(monitor.observe(budget(800)));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn count_fdinfo_watches() {
+        let fdinfo = "pos:\t0\nflags:\t02004000\nmnt_id:\t15\nino:\t1057\n\
+            inotify wd:2 ino:1a2b sdev:fd00 mask:fce ignored_mask:0 fhandle-bytes:8\n\
+            inotify wd:1 ino:1a2a sdev:fd00 mask:fce ignored_mask:0 fhandle-bytes:8\n";
+        assert_eq!(count_inotify_watches(fdinfo), 2);
+        assert_eq!(count_inotify_watches("pos:\t0\nflags:\t02\n"), 0);
     }
 
     #[test]
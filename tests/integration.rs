@@ -29,6 +29,26 @@ fn git(dir: &Path, args: &[&str]) {
     );
 }
 
+/// Start a watcher with its own state dir and wait for its first line.
+fn spawn_watcher(
+    dir: &Path,
+    state: &Path,
+    args: &[&str],
+) -> (std::process::Child, BufReader<std::process::ChildStdout>, String) {
+    let mut child = Command::new(gstat_binary())
+        .args(args)
+        .current_dir(dir)
+        .env("XDG_RUNTIME_DIR", state)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("failed to spawn gstat");
+    let mut reader = BufReader::new(child.stdout.take().unwrap());
+    let mut first = String::new();
+    reader.read_line(&mut first).unwrap();
+    (child, reader, first.trim().to_string())
+}
+
 fn init_repo(dir: &Path) {
     git(dir, &["init"]);
     git(dir, &["config", "user.email", "test@test.com"]);
@@ -62,105 +82,1011 @@ fn once_json_output() {
 }
 
 #[test]
-fn once_custom_format() {
+fn once_custom_format() {
+    let tmp = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+
+    std::fs::write(tmp.path().join("new.txt"), "new").unwrap();
+
+    let output = Command::new(gstat_binary())
+        .args(["--once", "--format", "{branch} ?{untracked}"])
+        .current_dir(tmp.path())
+        .output()
+        .expect("failed to run gstat");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.trim(), "master ?1");
+}
+
+#[test]
+fn once_staged_and_modified() {
+    let tmp = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+
+    std::fs::write(tmp.path().join("staged.txt"), "staged").unwrap();
+    git(tmp.path(), &["add", "staged.txt"]);
+    std::fs::write(tmp.path().join("file.txt"), "modified").unwrap();
+
+    let output = Command::new(gstat_binary())
+        .args(["--once", "--format", "+{staged} ~{modified}"])
+        .current_dir(tmp.path())
+        .output()
+        .expect("failed to run gstat");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.trim(), "+1 ~1");
+}
+
+#[test]
+fn once_detects_untracked_files() {
+    let tmp = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+
+    std::fs::write(tmp.path().join("a.txt"), "a").unwrap();
+    std::fs::write(tmp.path().join("b.txt"), "b").unwrap();
+    std::fs::write(tmp.path().join("c.txt"), "c").unwrap();
+
+    let output = Command::new(gstat_binary())
+        .args(["--once", "--format", "?{untracked}"])
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "?3");
+}
+
+#[test]
+fn once_not_a_repo() {
+    let tmp = tempfile::tempdir().unwrap();
+
+    let output = Command::new(gstat_binary())
+        .args(["--once"])
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("not a git repository"));
+}
+
+#[test]
+fn once_path_argument() {
+    let tmp = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+
+    std::fs::write(tmp.path().join("x.txt"), "x").unwrap();
+
+    let output = Command::new(gstat_binary())
+        .args(["--once", "--format", "?{untracked}"])
+        .arg(tmp.path())
+        .output()
+        .unwrap();
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "?1");
+}
+
+#[test]
+fn once_merge_state() {
+    let tmp = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+
+    std::fs::write(tmp.path().join(".git/MERGE_HEAD"), "abc123\n").unwrap();
+
+    let output = Command::new(gstat_binary())
+        .args(["--once", "--format", "{state}"])
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "merge");
+}
+
+#[test]
+fn once_dirty_and_changes() {
+    let tmp = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+
+    let run = || {
+        let output = Command::new(gstat_binary())
+            .args(["--once", "--format", "{dirty} {changes}"])
+            .current_dir(tmp.path())
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    };
+
+    assert_eq!(run(), "false 0");
+    std::fs::write(tmp.path().join("new.txt"), "new").unwrap();
+    std::fs::write(tmp.path().join("file.txt"), "modified").unwrap();
+    assert_eq!(run(), "true 2");
+}
+
+#[test]
+fn once_compact_preset() {
+    let tmp = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+    std::fs::write(tmp.path().join("new.txt"), "new").unwrap();
+
+    let output = Command::new(gstat_binary())
+        .args(["--once", "--preset", "compact"])
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "master ✗");
+}
+
+#[test]
+fn once_polybar_theme_override() {
+    let tmp = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+
+    let output = Command::new(gstat_binary())
+        .args(["--once", "--preset", "polybar", "--theme", "clean=#112233"])
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        "%{F#112233}master ✓%{F-}"
+    );
+
+    let output = Command::new(gstat_binary())
+        .args(["--once", "--preset", "i3blocks"])
+        .env("GIT_STATUS_WATCH_THEME", "clean=#abcdef,dirty=#000000")
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        "master ✓\nmaster\n#abcdef"
+    );
+
+    let output = Command::new(gstat_binary())
+        .args(["--once", "--preset", "polybar", "--theme", "clean=green"])
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success(), "invalid color should be rejected");
+}
+
+#[test]
+fn once_extended_conflict_breakdown() {
+    let tmp = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+    git(tmp.path(), &["checkout", "-b", "other"]);
+    std::fs::write(tmp.path().join("file.txt"), "theirs").unwrap();
+    git(tmp.path(), &["commit", "-am", "theirs"]);
+    git(tmp.path(), &["checkout", "master"]);
+    std::fs::write(tmp.path().join("file.txt"), "ours").unwrap();
+    git(tmp.path(), &["commit", "-am", "ours"]);
+    let merge = Command::new("git")
+        .args(["merge", "other"])
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+    assert!(!merge.status.success(), "merge should conflict");
+
+    let run = |args: &[&str]| {
+        let output = Command::new(gstat_binary())
+            .args(args)
+            .current_dir(tmp.path())
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        serde_json::from_str::<serde_json::Value>(stdout.trim()).unwrap()
+    };
+
+    let basic = run(&["--once"]);
+    assert_eq!(basic["conflicted"], 1);
+    assert!(basic.get("conflicts").is_none());
+
+    let extended = run(&["--once", "--extended"]);
+    assert_eq!(extended["conflicts"]["both_modified"], 1);
+    assert_eq!(extended["conflicts"]["deleted_by_us"], 0);
+}
+
+#[cfg(unix)]
+#[test]
+fn once_typechange() {
+    let tmp = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+    std::fs::remove_file(tmp.path().join("file.txt")).unwrap();
+    std::os::unix::fs::symlink("elsewhere", tmp.path().join("file.txt")).unwrap();
+
+    let output = Command::new(gstat_binary())
+        .args(["--once", "--format", "T{typechange} ~{modified} {changes}"])
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "T1 ~0 1");
+}
+
+#[test]
+fn once_hidden_changes() {
+    let tmp = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+    git(tmp.path(), &["update-index", "--assume-unchanged", "file.txt"]);
+    std::fs::write(tmp.path().join("file.txt"), "local edit").unwrap();
+
+    let output = Command::new(gstat_binary())
+        .args(["--once", "--format", "~{modified} h{hidden_changes}"])
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "~0 h0");
+
+    let output = Command::new(gstat_binary())
+        .args([
+            "--once",
+            "--hidden-changes",
+            "--format",
+            "~{modified} h{hidden_changes}",
+        ])
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "~0 h1");
+}
+
+#[test]
+fn once_hidden_changes_sparse_checkout() {
+    let tmp = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+    std::fs::create_dir(tmp.path().join("sub")).unwrap();
+    std::fs::write(tmp.path().join("sub/a.txt"), "a").unwrap();
+    git(tmp.path(), &["add", "."]);
+    git(tmp.path(), &["commit", "-m", "sub"]);
+    // Cone mode keeps only top-level files, so sub/a.txt gets skip-worktree.
+    git(tmp.path(), &["sparse-checkout", "init", "--cone"]);
+    git(tmp.path(), &["update-index", "--assume-unchanged", "file.txt"]);
+
+    let output = Command::new(gstat_binary())
+        .args(["--once", "--hidden-changes", "--format", "h{hidden_changes}"])
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "h1");
+}
+
+#[cfg(unix)]
+#[test]
+fn once_lfs_pending() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmp = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+    git(tmp.path(), &["remote", "add", "origin", "git@github.com:o/r.git"]);
+    git(tmp.path(), &["update-ref", "refs/remotes/origin/main", "HEAD"]);
+    git(tmp.path(), &["branch", "--set-upstream-to=origin/main"]);
+    std::fs::create_dir(tmp.path().join(".git/lfs")).unwrap();
+
+    // Stand-in for git-lfs: one unpushed object, one of whose paths is also
+    // still a pointer, plus a second pointer.
+    let bin = tempfile::tempdir().unwrap();
+    let script = bin.path().join("git-lfs");
+    std::fs::write(
+        &script,
+        r#"#!/bin/sh
+case "$*" in
+"ls-files origin/main HEAD")
+    echo "4d7a214614 - big.bin" ;;
+"ls-files")
+    echo "4d7a214614 - big.bin"
+    echo "a5c3fe0b21 - other.bin"
+    echo "0f1e2d3c4b * done.bin" ;;
+esac
+"#,
+    )
+    .unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!(
+        "{}:{}",
+        bin.path().display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+
+    let output = Command::new(gstat_binary())
+        .args(["--once", "--format", "lfs{lfs_pending}"])
+        .env("PATH", path)
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "lfs2");
+}
+
+#[test]
+fn once_rebase_paused_on_edit() {
+    let tmp = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+    std::fs::write(tmp.path().join("file.txt"), "second").unwrap();
+    git(tmp.path(), &["commit", "-am", "second"]);
+
+    let rebase = Command::new("git")
+        .args(["rebase", "-i", "HEAD~1"])
+        .env("GIT_SEQUENCE_EDITOR", "sed -i.bak s/^pick/edit/")
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+    assert!(rebase.status.success());
+
+    let output = Command::new(gstat_binary())
+        .args(["--once", "--format", "{state}"])
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "rebase:paused");
+}
+
+#[test]
+fn once_merge_conflicted_state() {
+    let tmp = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+    git(tmp.path(), &["checkout", "-b", "other"]);
+    std::fs::write(tmp.path().join("file.txt"), "theirs").unwrap();
+    git(tmp.path(), &["commit", "-am", "theirs"]);
+    git(tmp.path(), &["checkout", "master"]);
+    std::fs::write(tmp.path().join("file.txt"), "ours").unwrap();
+    git(tmp.path(), &["commit", "-am", "ours"]);
+    let merge = Command::new("git")
+        .args(["merge", "other"])
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+    assert!(!merge.status.success(), "merge should conflict");
+
+    let output = Command::new(gstat_binary())
+        .args(["--once", "--format", "{state}"])
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        "merge:conflicted"
+    );
+}
+
+#[test]
+fn once_merge_source() {
+    let tmp = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+    git(tmp.path(), &["checkout", "-b", "feature/x"]);
+    std::fs::write(tmp.path().join("other.txt"), "theirs").unwrap();
+    git(tmp.path(), &["add", "."]);
+    git(tmp.path(), &["commit", "-m", "theirs"]);
+    git(tmp.path(), &["checkout", "master"]);
+    git(tmp.path(), &["merge", "--no-ff", "--no-commit", "feature/x"]);
+
+    let output = Command::new(gstat_binary())
+        .args(["--once", "--format", "{state} {merging}"])
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        "merge feature/x"
+    );
+}
+
+#[test]
+fn once_cherry_pick_remaining() {
+    let tmp = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+    git(tmp.path(), &["checkout", "-b", "picks"]);
+    for n in 1..=3 {
+        std::fs::write(tmp.path().join("file.txt"), format!("pick {}", n)).unwrap();
+        git(tmp.path(), &["commit", "-am", &format!("pick {}", n)]);
+    }
+    git(tmp.path(), &["checkout", "master"]);
+    std::fs::write(tmp.path().join("file.txt"), "ours").unwrap();
+    git(tmp.path(), &["commit", "-am", "ours"]);
+
+    let pick = Command::new("git")
+        .args(["cherry-pick", "master..picks"])
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+    assert!(!pick.status.success(), "first pick should conflict");
+
+    let output = Command::new(gstat_binary())
+        .args(["--once", "--format", "{state} {op_remaining}"])
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        "cherry-pick:conflicted 3"
+    );
+}
+
+#[test]
+fn once_commit_info() {
+    let tmp = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+
+    let run = |args: &[&str]| {
+        let output = Command::new(gstat_binary())
+            .args(args)
+            .current_dir(tmp.path())
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    };
+
+    let template = "{commit_subject}|{commit_author}";
+    assert_eq!(run(&["--once", "--format", template]), "|");
+    assert_eq!(
+        run(&["--once", "--commit-info", "--format", template]),
+        "initial|Test"
+    );
+}
+
+#[test]
+fn once_and_follower_match_their_own_commit_info() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+    let template = "{commit_subject}|{commit_author}";
+    let once = |args: &[&str]| {
+        let output = Command::new(gstat_binary())
+            .args(args)
+            .current_dir(tmp.path())
+            .env("XDG_RUNTIME_DIR", state.path())
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    };
+
+    // Leader without --commit-info: callers that ask for it compute it
+    let (mut leader, _, first) = spawn_watcher(tmp.path(), state.path(), &["--format", template]);
+    assert_eq!(first, "|");
+    assert_eq!(
+        once(&["--once", "--commit-info", "--format", template]),
+        "initial|Test"
+    );
+    let (mut follower, _, first) = spawn_watcher(
+        tmp.path(),
+        state.path(),
+        &["--commit-info", "--format", template],
+    );
+    assert_eq!(first, "initial|Test");
+    follower.kill().unwrap();
+    let _ = follower.wait();
+    leader.kill().unwrap();
+    let _ = leader.wait();
+
+    // Leader with --commit-info: callers that don't ask for it don't get it
+    let (mut leader, _, first) = spawn_watcher(
+        tmp.path(),
+        state.path(),
+        &["--commit-info", "--format", template],
+    );
+    assert_eq!(first, "initial|Test");
+    assert_eq!(once(&["--once", "--format", template]), "|");
+    let (mut follower, _, first) = spawn_watcher(tmp.path(), state.path(), &["--format", template]);
+    assert_eq!(first, "|");
+    follower.kill().unwrap();
+    let _ = follower.wait();
+    leader.kill().unwrap();
+    let _ = leader.wait();
+}
+
+#[test]
+fn once_describe_tag() {
+    let tmp = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+    git(tmp.path(), &["tag", "-a", "v2.3.1", "-m", "release"]);
+    for n in 1..=2 {
+        std::fs::write(tmp.path().join("file.txt"), format!("change {}", n)).unwrap();
+        git(tmp.path(), &["commit", "-am", &format!("change {}", n)]);
+    }
+
+    let run = |args: &[&str]| {
+        let output = Command::new(gstat_binary())
+            .args(args)
+            .current_dir(tmp.path())
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    };
+
+    let template = "{tag}+{commits_since_tag}";
+    assert_eq!(run(&["--once", "--format", template]), "+0");
+    assert_eq!(
+        run(&["--once", "--describe", "--format", template]),
+        "v2.3.1+2"
+    );
+
+    let version = run(&["--once", "--describe", "--format", "{version}"]);
+    assert!(version.starts_with("2.3.1-2-g"), "version: {}", version);
+    assert_eq!(version.len(), "2.3.1-2-g".len() + 7, "version: {}", version);
+}
+
+#[test]
+fn once_describe_version_alongside_plain_leader() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+    git(tmp.path(), &["tag", "v0.4.0"]);
+    let once = |args: &[&str]| {
+        let output = Command::new(gstat_binary())
+            .args(args)
+            .current_dir(tmp.path())
+            .env("XDG_RUNTIME_DIR", state.path())
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    };
+
+    let (mut leader, _, first) = spawn_watcher(tmp.path(), state.path(), &["--format", "[{version}]"]);
+    assert_eq!(first, "[]");
+    assert_eq!(once(&["--once", "--describe", "--format", "[{version}]"]), "[0.4.0]");
+    leader.kill().unwrap();
+    let _ = leader.wait();
+
+    let (mut leader, _, first) = spawn_watcher(
+        tmp.path(),
+        state.path(),
+        &["--describe", "--format", "[{version}]"],
+    );
+    assert_eq!(first, "[0.4.0]");
+    assert_eq!(once(&["--once", "--format", "[{version}]"]), "[]");
+    leader.kill().unwrap();
+    let _ = leader.wait();
+}
+
+#[test]
+fn watch_describe_sees_nested_tag() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+    git(tmp.path(), &["tag", "v1/rc1"]);
+    std::fs::write(tmp.path().join("file.txt"), "change").unwrap();
+    git(tmp.path(), &["commit", "-am", "change"]);
+
+    let template = "{tag}+{commits_since_tag}";
+    let (mut child, mut reader, first) = spawn_watcher(
+        tmp.path(),
+        state.path(),
+        &["--describe", "--format", template],
+    );
+    assert_eq!(first, "v1/rc1+1");
+
+    // A caller without --describe doesn't get the leader's describe fields
+    let output = Command::new(gstat_binary())
+        .args(["--once", "--format", template])
+        .current_dir(tmp.path())
+        .env("XDG_RUNTIME_DIR", state.path())
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "+0");
+
+    // Only refs/tags/v1 changes, not refs/tags itself
+    std::thread::sleep(Duration::from_millis(500));
+    git(tmp.path(), &["tag", "v1/rc2"]);
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let mut line = String::new();
+        let _ = reader.read_line(&mut line);
+        let _ = tx.send(line);
+    });
+
+    match rx.recv_timeout(Duration::from_secs(5)) {
+        Ok(line) => assert_eq!(line.trim(), "v1/rc2+0"),
+        Err(_) => {
+            child.kill().unwrap();
+            panic!("timed out waiting for gstat to detect the nested tag");
+        }
+    }
+
+    child.kill().unwrap();
+    let _ = child.wait();
+}
+
+#[test]
+fn once_remote_and_host() {
+    let tmp = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+
+    let run = || {
+        let output = Command::new(gstat_binary())
+            .args(["--once", "--format", "{remote}@{remote_host}"])
+            .current_dir(tmp.path())
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    };
+
+    assert_eq!(run(), "@");
+
+    git(tmp.path(), &["remote", "add", "upstream", "git@github.com:o/r.git"]);
+    git(tmp.path(), &["update-ref", "refs/remotes/upstream/main", "HEAD"]);
+    git(tmp.path(), &["branch", "--set-upstream-to=upstream/main"]);
+    assert_eq!(run(), "upstream@github.com");
+
+    // Remote names may contain `/`
+    git(tmp.path(), &["remote", "add", "team/fork", "https://gitlab.com/o/r.git"]);
+    git(tmp.path(), &["update-ref", "refs/remotes/team/fork/main", "HEAD"]);
+    git(tmp.path(), &["branch", "--set-upstream-to=team/fork/main"]);
+    assert_eq!(run(), "team/fork@gitlab.com");
+
+    // insteadOf aliases resolve to the real host
+    git(tmp.path(), &["config", "url.git@codeberg.org:.insteadOf", "cb:"]);
+    git(tmp.path(), &["remote", "set-url", "team/fork", "cb:o/r.git"]);
+    assert_eq!(run(), "team/fork@codeberg.org");
+
+    // Tracking a local branch means no remote at all
+    git(tmp.path(), &["branch", "feature/x"]);
+    git(tmp.path(), &["branch", "--set-upstream-to=feature/x"]);
+    assert_eq!(run(), "@");
+}
+
+#[test]
+fn watch_picks_up_remote_set_url() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+    git(tmp.path(), &["remote", "add", "origin", "git@github.com:o/r.git"]);
+    git(tmp.path(), &["update-ref", "refs/remotes/origin/main", "HEAD"]);
+    git(tmp.path(), &["branch", "--set-upstream-to=origin/main"]);
+
+    let (mut child, mut reader, first) = spawn_watcher(
+        tmp.path(),
+        state.path(),
+        &["--format", "{remote}@{remote_host}"],
+    );
+    assert_eq!(first, "origin@github.com");
+
+    // The config write alone must trigger a recompute that does not reuse
+    // the cached host
+    std::thread::sleep(Duration::from_millis(500));
+    git(tmp.path(), &["remote", "set-url", "origin", "git@gitlab.com:o/r.git"]);
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).unwrap_or(0) == 0 || tx.send(line).is_err() {
+            return;
+        }
+    });
+
+    match rx.recv_timeout(Duration::from_secs(5)) {
+        Ok(line) => assert_eq!(line.trim(), "origin@gitlab.com"),
+        Err(_) => {
+            child.kill().unwrap();
+            panic!("timed out waiting for gstat to pick up the new URL");
+        }
+    }
+
+    git(tmp.path(), &["branch", "--unset-upstream"]);
+    match rx.recv_timeout(Duration::from_secs(5)) {
+        Ok(line) => assert_eq!(line.trim(), "@"),
+        Err(_) => {
+            child.kill().unwrap();
+            panic!("timed out waiting for gstat to drop the upstream");
+        }
+    }
+
+    child.kill().unwrap();
+    let _ = child.wait();
+}
+
+#[test]
+fn once_csv_output_with_header() {
+    let tmp = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+    std::fs::write(tmp.path().join("new.txt"), "new").unwrap();
+
+    let output = Command::new(gstat_binary())
+        .args(["--once", "--output", "csv", "--header"])
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 2, "stdout: {}", stdout);
+    let header: Vec<&str> = lines[0].split(',').collect();
+    let row: Vec<&str> = lines[1].split(',').collect();
+    assert_eq!(header.len(), row.len());
+    let col = |name: &str| row[header.iter().position(|h| *h == name).unwrap()];
+    assert_eq!(col("repo"), tmp.path().canonicalize().unwrap().to_str().unwrap());
+    assert_eq!(col("untracked"), "1");
+    assert_eq!(col("dirty"), "true");
+
+    let output = Command::new(gstat_binary())
+        .args(["--once", "--header"])
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success(), "--header without --output should fail");
+}
+
+#[test]
+fn once_osc_keeps_stdout_clean() {
+    let tmp = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+
+    let output = Command::new(gstat_binary())
+        .args(["--once", "--osc", "--format", "{branch}"])
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains('\x1b'), "stdout: {:?}", stdout);
+    assert!(!stdout.trim().is_empty());
+}
+
+#[test]
+fn once_log_file() {
+    let tmp = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+    let log = tmp.path().join("gstat.log");
+
+    let output = Command::new(gstat_binary())
+        .args(["--once", "--log-level", "debug", "--log-file"])
+        .arg(&log)
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty(), "logs should not go to stderr");
+    let contents = std::fs::read_to_string(&log).unwrap();
+    assert!(contents.contains("computed status"), "log: {}", contents);
+}
+
+#[test]
+fn log_file_alone_logs_at_info() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+    let log = state.path().join("gstat.log");
+    let log_arg = log.to_str().unwrap();
+
+    let (mut child, _, _) = spawn_watcher(tmp.path(), state.path(), &["--log-file", log_arg]);
+    child.kill().unwrap();
+    let _ = child.wait();
+
+    let contents = std::fs::read_to_string(&log).unwrap();
+    assert!(contents.contains("acquired lock"), "log: {}", contents);
+    assert!(!contents.contains("DEBUG"), "log: {}", contents);
+}
+
+// --- subcommand tests ---
+
+#[test]
+fn doctor_reports_findings() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+    let state_dir = state.path().join("git-status-watch");
+    std::fs::create_dir(&state_dir).unwrap();
+    std::fs::write(state_dir.join("%2Fno%2Fsuch%2Frepo"), "{}").unwrap();
+
+    let output = Command::new(gstat_binary())
+        .arg("doctor")
+        .current_dir(tmp.path())
+        .env("XDG_RUNTIME_DIR", state.path())
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("git version"), "stdout: {}", stdout);
+    assert!(stdout.contains("no watcher is running"), "stdout: {}", stdout);
+    assert!(stdout.contains("1 stale files"), "stdout: {}", stdout);
+    assert!(stdout.contains("watch backend: "), "stdout: {}", stdout);
+    assert!(!output.status.success(), "stale files should fail the check");
+
+    let output = Command::new(gstat_binary())
+        .args(["doctor", "--backend", "poll", "--poll-interval", "500ms"])
+        .current_dir(tmp.path())
+        .env("XDG_RUNTIME_DIR", state.path())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("watch backend: poll (every 500ms)"),
+        "stdout: {}",
+        stdout
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn doctor_reports_leader_inotify_watches() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+
+    let mut child = Command::new(gstat_binary())
+        .current_dir(tmp.path())
+        .env("XDG_RUNTIME_DIR", state.path())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("failed to spawn gstat");
+    let mut reader = BufReader::new(child.stdout.take().unwrap());
+    let mut initial = String::new();
+    reader.read_line(&mut initial).unwrap();
+    std::thread::sleep(Duration::from_millis(300));
+
+    let output = Command::new(gstat_binary())
+        .arg("doctor")
+        .current_dir(tmp.path())
+        .env("XDG_RUNTIME_DIR", state.path())
+        .output()
+        .unwrap();
+    child.kill().unwrap();
+    let _ = child.wait();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("watcher holds") && stdout.contains("in use by this user"),
+        "stdout: {}",
+        stdout
+    );
+}
+
+#[test]
+fn non_native_backend_is_rejected() {
     let tmp = tempfile::tempdir().unwrap();
     init_repo(tmp.path());
 
-    std::fs::write(tmp.path().join("new.txt"), "new").unwrap();
-
+    let backend = if cfg!(target_os = "linux") {
+        "kqueue"
+    } else {
+        "inotify"
+    };
     let output = Command::new(gstat_binary())
-        .args(["--once", "--format", "{branch} ?{untracked}"])
+        .args(["--backend", backend])
         .current_dir(tmp.path())
+        .env("XDG_RUNTIME_DIR", tmp.path())
         .output()
-        .expect("failed to run gstat");
-
-    assert!(output.status.success());
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    assert_eq!(stdout.trim(), "master ?1");
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("invalid value"), "stderr: {}", stderr);
 }
 
 #[test]
-fn once_staged_and_modified() {
+fn bench_reports_phases() {
     let tmp = tempfile::tempdir().unwrap();
     init_repo(tmp.path());
 
-    std::fs::write(tmp.path().join("staged.txt"), "staged").unwrap();
-    git(tmp.path(), &["add", "staged.txt"]);
-    std::fs::write(tmp.path().join("file.txt"), "modified").unwrap();
-
     let output = Command::new(gstat_binary())
-        .args(["--once", "--format", "+{staged} ~{modified}"])
+        .args(["bench", "-n", "3"])
         .current_dir(tmp.path())
         .output()
-        .expect("failed to run gstat");
+        .unwrap();
 
+    assert!(output.status.success());
     let stdout = String::from_utf8_lossy(&output.stdout);
-    assert_eq!(stdout.trim(), "+1 ~1");
+    for phase in ["porcelain", "stash", "state", "total"] {
+        assert!(stdout.contains(phase), "missing {}: {}", phase, stdout);
+    }
 }
 
 #[test]
-fn once_detects_untracked_files() {
+fn install_hooks_pokes_on_commit() {
     let tmp = tempfile::tempdir().unwrap();
     init_repo(tmp.path());
 
-    std::fs::write(tmp.path().join("a.txt"), "a").unwrap();
-    std::fs::write(tmp.path().join("b.txt"), "b").unwrap();
-    std::fs::write(tmp.path().join("c.txt"), "c").unwrap();
-
     let output = Command::new(gstat_binary())
-        .args(["--once", "--format", "?{untracked}"])
+        .arg("install-hooks")
         .current_dir(tmp.path())
         .output()
         .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("created   post-commit"), "stdout: {}", stdout);
 
-    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "?3");
+    std::fs::write(tmp.path().join("file.txt"), "changed").unwrap();
+    git(tmp.path(), &["commit", "-am", "second"]);
+    assert!(tmp.path().join(".git/git-status-watch.refresh").exists());
 }
 
 #[test]
-fn once_not_a_repo() {
+fn notify_without_watcher_fails() {
     let tmp = tempfile::tempdir().unwrap();
+    let state = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
 
     let output = Command::new(gstat_binary())
-        .args(["--once"])
+        .arg("notify")
         .current_dir(tmp.path())
+        .env("XDG_RUNTIME_DIR", state.path())
         .output()
         .unwrap();
 
     assert!(!output.status.success());
     let stderr = String::from_utf8_lossy(&output.stderr);
-    assert!(stderr.contains("not a git repository"));
+    assert!(stderr.contains("no watcher running"), "stderr: {}", stderr);
 }
 
+/// A lock held by someone other than the PID recorded in it (here: this
+/// test, standing in for a `--once` probe) must not get that PID signalled.
+#[cfg(target_os = "linux")]
 #[test]
-fn once_path_argument() {
+fn notify_ignores_stale_leader_pid() {
+    use std::os::unix::io::AsRawFd;
+
     let tmp = tempfile::tempdir().unwrap();
+    let state = tempfile::tempdir().unwrap();
     init_repo(tmp.path());
 
-    std::fs::write(tmp.path().join("x.txt"), "x").unwrap();
+    let mut bystander = Command::new("sleep").arg("30").spawn().unwrap();
+    let root = tmp.path().canonicalize().unwrap();
+    let state_dir = state.path().join("git-status-watch");
+    std::fs::create_dir(&state_dir).unwrap();
+    let lock_path = state_dir.join(format!(
+        "{}.lock",
+        root.to_string_lossy().replace('/', "%2F")
+    ));
+    std::fs::write(&lock_path, bystander.id().to_string()).unwrap();
+    let lock = std::fs::File::open(&lock_path).unwrap();
+    assert_eq!(unsafe { libc::flock(lock.as_raw_fd(), libc::LOCK_EX) }, 0);
 
     let output = Command::new(gstat_binary())
-        .args(["--once", "--format", "?{untracked}"])
-        .arg(tmp.path())
+        .arg("notify")
+        .current_dir(tmp.path())
+        .env("XDG_RUNTIME_DIR", state.path())
         .output()
         .unwrap();
 
-    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "?1");
+    let still_running = bystander.try_wait().unwrap().is_none();
+    bystander.kill().unwrap();
+    let _ = bystander.wait();
+    assert!(!output.status.success());
+    assert!(still_running, "the recorded PID must not be signalled");
 }
 
 #[test]
-fn once_merge_state() {
+fn read_cached_status_without_watcher() {
     let tmp = tempfile::tempdir().unwrap();
+    let state = tempfile::tempdir().unwrap();
     init_repo(tmp.path());
-
-    std::fs::write(tmp.path().join(".git/MERGE_HEAD"), "abc123\n").unwrap();
-
-    let output = Command::new(gstat_binary())
-        .args(["--once", "--format", "{state}"])
+    std::fs::create_dir(tmp.path().join("sub")).unwrap();
+
+    let read = || {
+        Command::new(gstat_binary())
+            .args(["read", "--format", "{branch} {stale}"])
+            .current_dir(tmp.path().join("sub"))
+            .env("XDG_RUNTIME_DIR", state.path())
+            .output()
+            .unwrap()
+    };
+
+    let missing = read();
+    assert!(!missing.status.success());
+    assert!(String::from_utf8_lossy(&missing.stderr).contains("no cached status"));
+
+    let once = Command::new(gstat_binary())
+        .arg("--once")
         .current_dir(tmp.path())
+        .env("XDG_RUNTIME_DIR", state.path())
         .output()
         .unwrap();
+    assert!(once.status.success());
 
-    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "merge");
+    let cached = read();
+    assert!(cached.status.success());
+    assert_eq!(String::from_utf8_lossy(&cached.stdout).trim(), "master stale");
 }
 
 // --- watch mode tests ---
@@ -210,6 +1136,120 @@ fn watch_detects_new_file() {
     let _ = child.wait();
 }
 
+#[test]
+fn watch_emits_final_state_after_storm() {
+    let tmp = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+
+    let mut child = Command::new(gstat_binary())
+        .args(["--format", "?{untracked}"])
+        .current_dir(tmp.path())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn gstat");
+
+    let stdout = child.stdout.take().unwrap();
+    let mut reader = BufReader::new(stdout);
+
+    let mut initial = String::new();
+    reader.read_line(&mut initial).unwrap();
+    assert_eq!(initial.trim(), "?0");
+
+    std::thread::sleep(Duration::from_millis(500));
+    for n in 0..1000 {
+        std::fs::write(tmp.path().join(format!("f{}.txt", n)), "x").unwrap();
+    }
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let mut line = String::new();
+        while reader.read_line(&mut line).unwrap_or(0) > 0 {
+            if tx.send(line.trim().to_string()).is_err() {
+                return;
+            }
+            line.clear();
+        }
+    });
+
+    let mut last = String::new();
+    while let Ok(line) = rx.recv_timeout(Duration::from_secs(5)) {
+        last = line;
+        if last == "?1000" {
+            break;
+        }
+    }
+    child.kill().unwrap();
+    let _ = child.wait();
+    assert_eq!(last, "?1000", "final state after the storm should be emitted");
+}
+
+#[test]
+fn watch_poll_backend_detects_new_file() {
+    let tmp = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+
+    let mut child = Command::new(gstat_binary())
+        .args([
+            "--format",
+            "?{untracked}",
+            "--backend",
+            "poll",
+            "--poll-interval",
+            "200ms",
+        ])
+        .current_dir(tmp.path())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn gstat");
+
+    let stdout = child.stdout.take().unwrap();
+    let mut reader = BufReader::new(stdout);
+
+    let mut initial = String::new();
+    reader.read_line(&mut initial).unwrap();
+    assert_eq!(initial.trim(), "?0");
+
+    std::thread::sleep(Duration::from_millis(500));
+    std::fs::write(tmp.path().join("newfile.txt"), "hello").unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let mut line = String::new();
+        let _ = reader.read_line(&mut line);
+        let _ = tx.send(line);
+    });
+
+    let result = rx.recv_timeout(Duration::from_secs(5));
+    child.kill().unwrap();
+    let _ = child.wait();
+    match result {
+        Ok(line) => assert_eq!(line.trim(), "?1", "poll backend should see new file"),
+        Err(_) => panic!("timed out waiting for the poll backend"),
+    }
+}
+
+#[test]
+fn poll_interval_rejects_bad_units() {
+    let output = Command::new(gstat_binary())
+        .args(["--once", "--poll-interval", "2h"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("invalid duration unit"), "stderr: {}", stderr);
+
+    let output = Command::new(gstat_binary())
+        .args(["--once", "--poll-interval", "999999999999999999m"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("invalid duration"), "stderr: {}", stderr);
+    assert!(!stderr.contains("panicked"), "stderr: {}", stderr);
+}
+
 #[test]
 fn watch_detects_git_add() {
     let tmp = tempfile::tempdir().unwrap();
@@ -470,3 +1510,178 @@ fn watch_detects_commit() {
     child.kill().unwrap();
     let _ = child.wait();
 }
+
+#[test]
+fn watch_notify_forces_recompute() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+    std::fs::write(tmp.path().join("ignored.txt"), "x").unwrap();
+
+    let mut child = Command::new(gstat_binary())
+        .args(["--format", "?{untracked}"])
+        .current_dir(tmp.path())
+        .env("XDG_RUNTIME_DIR", state.path())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn gstat");
+
+    let stdout = child.stdout.take().unwrap();
+    let mut reader = BufReader::new(stdout);
+
+    let mut initial = String::new();
+    reader.read_line(&mut initial).unwrap();
+    assert_eq!(initial.trim(), "?1");
+
+    // .git/info is filtered out by the watcher, so only notify can surface this
+    std::thread::sleep(Duration::from_millis(500));
+    std::fs::write(tmp.path().join(".git/info/exclude"), "ignored.txt\n").unwrap();
+
+    let output = Command::new(gstat_binary())
+        .arg("notify")
+        .current_dir(tmp.path())
+        .env("XDG_RUNTIME_DIR", state.path())
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "notify failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let mut line = String::new();
+        let _ = reader.read_line(&mut line);
+        let _ = tx.send(line);
+    });
+
+    match rx.recv_timeout(Duration::from_secs(5)) {
+        Ok(line) => {
+            assert_eq!(line.trim(), "?0", "notify should trigger a recompute");
+        }
+        Err(_) => {
+            child.kill().unwrap();
+            panic!("timed out waiting for gstat to handle notify");
+        }
+    }
+
+    child.kill().unwrap();
+    let _ = child.wait();
+}
+
+#[test]
+fn list_shows_running_watcher() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+
+    let mut child = Command::new(gstat_binary())
+        .args(["--format", "{branch}"])
+        .current_dir(tmp.path())
+        .env("XDG_RUNTIME_DIR", state.path())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn gstat");
+
+    let mut reader = BufReader::new(child.stdout.take().unwrap());
+    let mut initial = String::new();
+    reader.read_line(&mut initial).unwrap();
+
+    let output = Command::new(gstat_binary())
+        .args(["list", "--format", "{branch}"])
+        .env("XDG_RUNTIME_DIR", state.path())
+        .output()
+        .unwrap();
+
+    child.kill().unwrap();
+    let _ = child.wait();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let repo = tmp.path().canonicalize().unwrap();
+    let row = stdout
+        .lines()
+        .find(|l| l.contains(&*repo.to_string_lossy()))
+        .unwrap_or_else(|| panic!("repo not listed: {}", stdout));
+    assert!(row.starts_with(&child.id().to_string()), "row: {}", row);
+    assert!(row.ends_with("master"), "row: {}", row);
+}
+
+#[test]
+fn list_output_rows_name_the_repo() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+
+    let (mut child, _, _) = spawn_watcher(tmp.path(), state.path(), &[]);
+
+    let output = Command::new(gstat_binary())
+        .args(["list", "--output", "tsv", "--header"])
+        .env("XDG_RUNTIME_DIR", state.path())
+        .output()
+        .unwrap();
+
+    child.kill().unwrap();
+    let _ = child.wait();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 2, "stdout: {}", stdout);
+    assert!(lines[0].starts_with("repo\tbranch\t"), "header: {}", lines[0]);
+    let repo = tmp.path().canonicalize().unwrap();
+    let expected = format!("{}\tmaster\t", repo.display());
+    assert!(lines[1].starts_with(&expected), "row: {}", lines[1]);
+}
+
+#[test]
+fn watch_git_debounce_is_independent() {
+    let tmp = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+    std::fs::write(tmp.path().join("newfile.txt"), "hello").unwrap();
+
+    // Worktree events would wait 10s; index writes should land in ~20ms
+    let mut child = Command::new(gstat_binary())
+        .args([
+            "--format",
+            "+{staged}",
+            "--debounce-ms",
+            "10000",
+            "--git-debounce-ms",
+            "20",
+        ])
+        .current_dir(tmp.path())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn gstat");
+
+    let mut reader = BufReader::new(child.stdout.take().unwrap());
+    let mut initial = String::new();
+    reader.read_line(&mut initial).unwrap();
+    assert_eq!(initial.trim(), "+0");
+
+    std::thread::sleep(Duration::from_millis(500));
+    git(tmp.path(), &["add", "newfile.txt"]);
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let mut line = String::new();
+        let _ = reader.read_line(&mut line);
+        let _ = tx.send(line);
+    });
+
+    match rx.recv_timeout(Duration::from_secs(3)) {
+        Ok(line) => assert_eq!(line.trim(), "+1"),
+        Err(_) => {
+            child.kill().unwrap();
+            panic!("git event waited for the worktree debounce window");
+        }
+    }
+
+    child.kill().unwrap();
+    let _ = child.wait();
+}